@@ -0,0 +1,254 @@
+use crate::Image;
+
+const INSIDE: u8 = 0;
+const LEFT: u8 = 1;
+const RIGHT: u8 = 2;
+const BOTTOM: u8 = 4;
+const TOP: u8 = 8;
+const NEAR: u8 = 16;
+
+/// A corner of a polygon being clipped.
+///
+/// edge is true when the side running from this vertex to the next one is part of the
+/// original polygon, and false when it was made by cutting along a clipping boundary.
+#[derive(Copy, Clone)]
+pub struct ClipVertex {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub edge: bool,
+}
+
+impl ClipVertex {
+    pub fn new(x: f32, y: f32, z: f32) -> ClipVertex {
+        ClipVertex {
+            x,
+            y,
+            z,
+            edge: true,
+        }
+    }
+
+    fn lerp(&self, other: &ClipVertex, t: f32, edge: bool) -> ClipVertex {
+        ClipVertex {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            z: self.z + (other.z - self.z) * t,
+            edge,
+        }
+    }
+}
+
+impl Image {
+    fn outcode(&self, x: f32, y: f32, z: f32) -> u8 {
+        let mut code = INSIDE;
+        if x < 0.0 {
+            code |= LEFT;
+        } else if x > (self.width - 1) as f32 {
+            code |= RIGHT;
+        }
+        if y < 0.0 {
+            code |= BOTTOM;
+        } else if y > (self.height - 1) as f32 {
+            code |= TOP;
+        }
+        if z > self.near {
+            code |= NEAR;
+        }
+        code
+    }
+
+    /// Cohen-Sutherland clipping of the line from (x0, y0, z0) to (x1, y1, z1)
+    /// against the screen and the near plane.
+    ///
+    /// Returns: the part of the line that can be seen, or None if none of it can
+    pub fn clip_line(
+        &self,
+        mut x0: f32,
+        mut y0: f32,
+        mut z0: f32,
+        mut x1: f32,
+        mut y1: f32,
+        mut z1: f32,
+    ) -> Option<(f32, f32, f32, f32, f32, f32)> {
+        let max_x = (self.width - 1) as f32;
+        let max_y = (self.height - 1) as f32;
+        let mut code0 = self.outcode(x0, y0, z0);
+        let mut code1 = self.outcode(x1, y1, z1);
+        loop {
+            if code0 | code1 == INSIDE {
+                return Some((x0, y0, z0, x1, y1, z1));
+            }
+            if code0 & code1 != INSIDE {
+                // both ends are on the outside of the same boundary
+                return None;
+            }
+            let code = if code0 != INSIDE { code0 } else { code1 };
+            // how far along the line the boundary is crossed
+            let t = if code & NEAR != 0 {
+                (self.near - z0) / (z1 - z0)
+            } else if code & TOP != 0 {
+                (max_y - y0) / (y1 - y0)
+            } else if code & BOTTOM != 0 {
+                -y0 / (y1 - y0)
+            } else if code & RIGHT != 0 {
+                (max_x - x0) / (x1 - x0)
+            } else {
+                -x0 / (x1 - x0)
+            };
+            let x = x0 + (x1 - x0) * t;
+            let y = y0 + (y1 - y0) * t;
+            let z = z0 + (z1 - z0) * t;
+            // snap onto the boundary so rounding can't leave the point just outside
+            let (x, y, z) = if code & NEAR != 0 {
+                (x, y, self.near)
+            } else if code & (TOP | BOTTOM) != 0 {
+                (x, if code & TOP != 0 { max_y } else { 0.0 }, z)
+            } else {
+                (if code & RIGHT != 0 { max_x } else { 0.0 }, y, z)
+            };
+            if code == code0 {
+                x0 = x;
+                y0 = y;
+                z0 = z;
+                code0 = self.outcode(x0, y0, z0);
+            } else {
+                x1 = x;
+                y1 = y;
+                z1 = z;
+                code1 = self.outcode(x1, y1, z1);
+            }
+        }
+    }
+
+    /// Sutherland-Hodgman clipping of a polygon against the near plane and then
+    /// each side of the screen.
+    ///
+    /// Returns: the vertices of the visible part of the polygon, which is empty if none of it can be seen
+    pub fn clip_polygon(&self, mut vertices: Vec<ClipVertex>) -> Vec<ClipVertex> {
+        let max_x = (self.width - 1) as f32;
+        let max_y = (self.height - 1) as f32;
+        // each boundary is a plane (a, b, c, d) where a*x + b*y + c*z + d >= 0 on the visible side
+        let mut boundaries = vec![
+            [1.0, 0.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0, max_x],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, -1.0, 0.0, max_y],
+        ];
+        if self.near.is_finite() {
+            boundaries.insert(0, [0.0, 0.0, -1.0, self.near]);
+        }
+        for [a, b, c, d] in boundaries {
+            let inside = |v: &ClipVertex| a * v.x + b * v.y + c * v.z + d;
+            if vertices.is_empty() {
+                break;
+            }
+            let mut result = vec![];
+            for i in 0..vertices.len() {
+                let start = vertices[i];
+                let end = vertices[(i + 1) % vertices.len()];
+                let d_start = inside(&start);
+                let d_end = inside(&end);
+                if d_end >= 0.0 {
+                    if d_start < 0.0 {
+                        // coming back in, the cut point continues along the original side
+                        let t = d_start / (d_start - d_end);
+                        result.push(start.lerp(&end, t, start.edge));
+                    }
+                    result.push(end);
+                } else if d_start >= 0.0 {
+                    // going out, the side from the cut point runs along the boundary
+                    let t = d_start / (d_start - d_end);
+                    result.push(start.lerp(&end, t, false));
+                }
+            }
+            vertices = result;
+        }
+        vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_crossing_the_edge_is_cut_at_it() {
+        let screen = Image::new(500, 500);
+        assert_eq!(
+            screen.clip_line(250.0, 250.0, 0.0, 600.0, 250.0, 0.0),
+            Some((250.0, 250.0, 0.0, 499.0, 250.0, 0.0))
+        );
+        // a diagonal line through the bottom left corner region
+        let (x0, y0, _, x1, y1, _) = screen
+            .clip_line(-100.0, 100.0, 0.0, 100.0, -100.0, 0.0)
+            .unwrap();
+        assert_eq!((x0, y0, x1, y1), (0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn line_inside_is_unchanged() {
+        let screen = Image::new(500, 500);
+        assert_eq!(
+            screen.clip_line(10.0, 20.0, 5.0, 30.0, 40.0, -5.0),
+            Some((10.0, 20.0, 5.0, 30.0, 40.0, -5.0))
+        );
+    }
+
+    #[test]
+    fn line_off_screen_is_gone() {
+        let screen = Image::new(500, 500);
+        assert_eq!(screen.clip_line(-10.0, -10.0, 0.0, -5.0, 600.0, 0.0), None);
+        assert_eq!(screen.clip_line(600.0, 10.0, 0.0, 700.0, 20.0, 0.0), None);
+    }
+
+    #[test]
+    fn line_crossing_the_near_plane_is_cut_at_it() {
+        let mut screen = Image::new(500, 500);
+        screen.near = 0.0;
+        assert_eq!(
+            screen.clip_line(10.0, 10.0, -10.0, 30.0, 10.0, 10.0),
+            Some((10.0, 10.0, -10.0, 20.0, 10.0, 0.0))
+        );
+        assert_eq!(screen.clip_line(10.0, 10.0, 1.0, 30.0, 10.0, 10.0), None);
+    }
+
+    #[test]
+    fn triangle_crossing_the_near_plane_loses_a_corner() {
+        let mut screen = Image::new(500, 500);
+        screen.near = 0.0;
+        let clipped = screen.clip_polygon(vec![
+            ClipVertex::new(100.0, 100.0, -10.0),
+            ClipVertex::new(200.0, 100.0, 10.0),
+            ClipVertex::new(100.0, 200.0, -10.0),
+        ]);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|v| v.z <= 0.0));
+        assert_eq!(clipped.iter().filter(|v| v.z == 0.0).count(), 2);
+        // only the side cut along the near plane is new
+        assert_eq!(clipped.iter().filter(|v| !v.edge).count(), 1);
+    }
+
+    #[test]
+    fn triangle_inside_is_unchanged() {
+        let screen = Image::new(500, 500);
+        let clipped = screen.clip_polygon(vec![
+            ClipVertex::new(100.0, 100.0, 0.0),
+            ClipVertex::new(200.0, 100.0, 0.0),
+            ClipVertex::new(100.0, 200.0, 0.0),
+        ]);
+        assert_eq!(clipped.len(), 3);
+        assert!(clipped.iter().all(|v| v.edge));
+    }
+
+    #[test]
+    fn triangle_off_screen_is_gone() {
+        let screen = Image::new(500, 500);
+        let clipped = screen.clip_polygon(vec![
+            ClipVertex::new(-100.0, 100.0, 0.0),
+            ClipVertex::new(-10.0, 100.0, 0.0),
+            ClipVertex::new(-50.0, 200.0, 0.0),
+        ]);
+        assert!(clipped.is_empty());
+    }
+}
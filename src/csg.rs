@@ -0,0 +1,418 @@
+use crate::gmath::{cross_product, dot_product, normalize, subtract};
+use crate::matrix::Matrix;
+
+/// How close to a plane a point has to be to count as being on it
+const EPSILON: f32 = 1e-3;
+
+const COPLANAR: u8 = 0;
+const FRONT: u8 = 1;
+const BACK: u8 = 2;
+const SPANNING: u8 = 3;
+
+/// The ways two solids can be combined
+pub enum Operation {
+    /// everything that is in either solid
+    Union,
+    /// only what is in both solids
+    Intersection,
+    /// what is in the first solid but not the second
+    Difference,
+}
+
+/// The plane of points p where normal . p = w, with normal pointing out of the front
+#[derive(Copy, Clone)]
+struct Plane {
+    normal: [f32; 3],
+    w: f32,
+}
+
+impl Plane {
+    fn from_points(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> Option<Plane> {
+        let mut normal = cross_product(&subtract(b, a), &subtract(c, a));
+        if dot_product(&normal, &normal) == 0.0 {
+            return None;
+        }
+        normalize(&mut normal);
+        Some(Plane {
+            normal,
+            w: dot_product(&normal, a),
+        })
+    }
+
+    fn flip(&mut self) {
+        self.normal = [-self.normal[0], -self.normal[1], -self.normal[2]];
+        self.w = -self.w;
+    }
+
+    /// Sorts polygon into the list for where it is compared to this plane, cutting it in two if it
+    /// crosses the plane. Polygons in the plane go in coplanar_front or coplanar_back depending on
+    /// which way they face
+    fn split_polygon(
+        &self,
+        polygon: Polygon,
+        coplanar_front: &mut Vec<Polygon>,
+        coplanar_back: &mut Vec<Polygon>,
+        front: &mut Vec<Polygon>,
+        back: &mut Vec<Polygon>,
+    ) {
+        let types: Vec<u8> = polygon
+            .vertices
+            .iter()
+            .map(|v| {
+                let t = dot_product(&self.normal, v) - self.w;
+                if t < -EPSILON {
+                    BACK
+                } else if t > EPSILON {
+                    FRONT
+                } else {
+                    COPLANAR
+                }
+            })
+            .collect();
+        match types.iter().fold(COPLANAR, |all, t| all | t) {
+            COPLANAR => {
+                if dot_product(&self.normal, &polygon.plane.normal) > 0.0 {
+                    coplanar_front.push(polygon);
+                } else {
+                    coplanar_back.push(polygon);
+                }
+            }
+            FRONT => front.push(polygon),
+            BACK => back.push(polygon),
+            _ => {
+                let mut f = vec![];
+                let mut b = vec![];
+                let count = polygon.vertices.len();
+                for i in 0..count {
+                    let j = (i + 1) % count;
+                    let (ti, tj) = (types[i], types[j]);
+                    let (vi, vj) = (polygon.vertices[i], polygon.vertices[j]);
+                    if ti != BACK {
+                        f.push(vi);
+                    }
+                    if ti != FRONT {
+                        b.push(vi);
+                    }
+                    if ti | tj == SPANNING {
+                        let direction = subtract(&vj, &vi);
+                        let t = (self.w - dot_product(&self.normal, &vi))
+                            / dot_product(&self.normal, &direction);
+                        let v = [
+                            vi[0] + direction[0] * t,
+                            vi[1] + direction[1] * t,
+                            vi[2] + direction[2] * t,
+                        ];
+                        f.push(v);
+                        b.push(v);
+                    }
+                }
+                if f.len() >= 3 {
+                    front.push(Polygon {
+                        vertices: f,
+                        plane: polygon.plane,
+                    });
+                }
+                if b.len() >= 3 {
+                    back.push(Polygon {
+                        vertices: b,
+                        plane: polygon.plane,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// A flat convex polygon, with its corners going counterclockwise when seen from the front
+#[derive(Clone)]
+struct Polygon {
+    vertices: Vec<[f32; 3]>,
+    plane: Plane,
+}
+
+impl Polygon {
+    fn flip(&mut self) {
+        self.vertices.reverse();
+        self.plane.flip();
+    }
+}
+
+/// A node of a BSP tree, where everything in front of plane is in the front tree, everything
+/// behind it is in the back tree, and polygons holds the polygons lying in the plane
+#[derive(Default)]
+struct Node {
+    plane: Option<Plane>,
+    front: Option<Box<Node>>,
+    back: Option<Box<Node>>,
+    polygons: Vec<Polygon>,
+}
+
+impl Node {
+    fn new(polygons: Vec<Polygon>) -> Node {
+        let mut node = Node::default();
+        node.build(polygons);
+        node
+    }
+
+    /// Turns the solid inside out
+    fn invert(&mut self) {
+        for polygon in self.polygons.iter_mut() {
+            polygon.flip();
+        }
+        if let Some(plane) = self.plane.as_mut() {
+            plane.flip();
+        }
+        if let Some(front) = self.front.as_mut() {
+            front.invert();
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.invert();
+        }
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// Removes the parts of polygons that are inside the solid this tree makes
+    fn clip_polygons(&self, polygons: Vec<Polygon>) -> Vec<Polygon> {
+        let plane = match self.plane {
+            Some(plane) => plane,
+            None => return polygons,
+        };
+        let mut front = vec![];
+        let mut back = vec![];
+        for polygon in polygons {
+            let mut coplanar_front = vec![];
+            let mut coplanar_back = vec![];
+            plane.split_polygon(
+                polygon,
+                &mut coplanar_front,
+                &mut coplanar_back,
+                &mut front,
+                &mut back,
+            );
+            front.append(&mut coplanar_front);
+            back.append(&mut coplanar_back);
+        }
+        if let Some(node) = &self.front {
+            front = node.clip_polygons(front);
+        }
+        match &self.back {
+            Some(node) => back = node.clip_polygons(back),
+            // nothing is left of the back of a leaf, since that is inside the solid
+            None => back.clear(),
+        }
+        front.append(&mut back);
+        front
+    }
+
+    /// Removes the parts of every polygon in this tree that are inside the solid bsp makes
+    fn clip_to(&mut self, bsp: &Node) {
+        self.polygons = bsp.clip_polygons(std::mem::take(&mut self.polygons));
+        if let Some(front) = self.front.as_mut() {
+            front.clip_to(bsp);
+        }
+        if let Some(back) = self.back.as_mut() {
+            back.clip_to(bsp);
+        }
+    }
+
+    fn all_polygons(&self) -> Vec<Polygon> {
+        let mut polygons = self.polygons.clone();
+        if let Some(front) = &self.front {
+            polygons.append(&mut front.all_polygons());
+        }
+        if let Some(back) = &self.back {
+            polygons.append(&mut back.all_polygons());
+        }
+        polygons
+    }
+
+    /// Adds polygons to the tree, splitting them up by the planes already in it
+    fn build(&mut self, polygons: Vec<Polygon>) {
+        if polygons.is_empty() {
+            return;
+        }
+        let plane = *self.plane.get_or_insert(polygons[0].plane);
+        let mut front = vec![];
+        let mut back = vec![];
+        for polygon in polygons {
+            let mut coplanar_front = vec![];
+            let mut coplanar_back = vec![];
+            plane.split_polygon(
+                polygon,
+                &mut coplanar_front,
+                &mut coplanar_back,
+                &mut front,
+                &mut back,
+            );
+            self.polygons.append(&mut coplanar_front);
+            self.polygons.append(&mut coplanar_back);
+        }
+        if !front.is_empty() {
+            self.front.get_or_insert_with(Box::default).build(front);
+        }
+        if !back.is_empty() {
+            self.back.get_or_insert_with(Box::default).build(back);
+        }
+    }
+}
+
+/// The triangles of a polygon matrix, skipping any that have no area
+fn to_polygons(polygons: &Matrix) -> Vec<Polygon> {
+    let mut result = vec![];
+    if polygons.matrix_array.is_empty() {
+        return result;
+    }
+    let point = |i: usize| {
+        [
+            polygons.matrix_array[0][i],
+            polygons.matrix_array[1][i],
+            polygons.matrix_array[2][i],
+        ]
+    };
+    for i in (0..polygons.matrix_array[0].len().saturating_sub(2)).step_by(3) {
+        let vertices = vec![point(i), point(i + 1), point(i + 2)];
+        if let Some(plane) = Plane::from_points(&vertices[0], &vertices[1], &vertices[2]) {
+            result.push(Polygon { vertices, plane });
+        }
+    }
+    result
+}
+
+impl Matrix {
+    /// add_csg()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * a
+    ///             struct matrix * b
+    ///             enum Operation operation
+    /// Returns:
+    ///
+    /// adds the triangles for the solid made by combining the solids in the polygon matrices
+    /// a and b. Each of them should be closed, with every triangle facing out.
+    ///
+    /// Works like csg.js: each solid is put in a BSP tree, the parts of each solid inside
+    /// (or for an intersection, outside) the other one are clipped away, and what is left is joined.
+    pub fn add_csg(&mut self, a: &Matrix, b: &Matrix, operation: &Operation) {
+        let mut a = Node::new(to_polygons(a));
+        let mut b = Node::new(to_polygons(b));
+        match operation {
+            Operation::Union => {
+                a.clip_to(&b);
+                b.clip_to(&a);
+                // take away the faces of b that are in the same place as faces of a
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+            }
+            Operation::Intersection => {
+                a.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                a.build(b.all_polygons());
+                a.invert();
+            }
+            Operation::Difference => {
+                a.invert();
+                a.clip_to(&b);
+                b.clip_to(&a);
+                b.invert();
+                b.clip_to(&a);
+                b.invert();
+                a.build(b.all_polygons());
+                a.invert();
+            }
+        }
+        for polygon in a.all_polygons() {
+            let [ax, ay, az] = polygon.vertices[0];
+            for p in 1..polygon.vertices.len() - 1 {
+                let [bx, by, bz] = polygon.vertices[p];
+                let [cx, cy, cz] = polygon.vertices[p + 1];
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::mesh::{Mesh, WELD_EPSILON};
+    use std::collections::HashMap;
+
+    /// The volume inside a closed polygon matrix, which is negative if its triangles face inward
+    pub(crate) fn volume(polygons: &Matrix) -> f32 {
+        let m = &polygons.matrix_array;
+        let point = |i: usize| [m[0][i], m[1][i], m[2][i]];
+        (0..m[0].len() / 3)
+            .map(|t| {
+                let (a, b, c) = (point(t * 3), point(t * 3 + 1), point(t * 3 + 2));
+                dot_product(&a, &cross_product(&b, &c)) / 6.0
+            })
+            .sum()
+    }
+
+    /// Panics unless every edge of polygons is shared with exactly one other triangle that goes
+    /// along it the other way, so the surface has no holes and faces the same way all over
+    pub(crate) fn assert_closed(polygons: &Matrix) {
+        let mesh = Mesh::from_polygons(polygons, WELD_EPSILON);
+        assert!(!mesh.indices.is_empty());
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for face in &mesh.indices {
+            for k in 0..3 {
+                *edges.entry((face[k], face[(k + 1) % 3])).or_default() += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            let point = |n: usize| mesh.positions[n];
+            assert_eq!(count, 1, "edge {:?} {:?} is used twice", point(a), point(b));
+            let back = edges.get(&(b, a));
+            assert_eq!(back, Some(&1), "edge {:?} {:?} is open", point(a), point(b));
+        }
+    }
+
+    /// Two 2x2x2 boxes that overlap in a 1x1x1 cube
+    fn boxes() -> (Matrix, Matrix) {
+        let mut a = Matrix::new(0, 0);
+        a.add_box(0.0, 0.0, 0.0, 2.0, 2.0, 2.0);
+        let mut b = Matrix::new(0, 0);
+        b.add_box(1.0, -1.0, -1.0, 2.0, 2.0, 2.0);
+        (a, b)
+    }
+
+    fn combined_volume(operation: Operation) -> f32 {
+        let (a, b) = boxes();
+        let mut result = Matrix::new(0, 0);
+        result.add_csg(&a, &b, &operation);
+        volume(&result)
+    }
+
+    #[test]
+    fn volumes_add_up() {
+        let (a, _) = boxes();
+        assert!((volume(&a) - 8.0).abs() < 1e-3);
+        assert!((combined_volume(Operation::Union) - 15.0).abs() < 1e-3);
+        assert!((combined_volume(Operation::Intersection) - 1.0).abs() < 1e-3);
+        assert!((combined_volume(Operation::Difference) - 7.0).abs() < 1e-3);
+        for operation in [Operation::Union, Operation::Intersection, Operation::Difference] {
+            let (a, b) = boxes();
+            let mut result = Matrix::new(0, 0);
+            result.add_csg(&a, &b, &operation);
+            assert_closed(&result);
+        }
+    }
+
+    #[test]
+    fn apart_shapes_have_no_intersection() {
+        let (a, _) = boxes();
+        let mut b = Matrix::new(0, 0);
+        b.add_box(10.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+        let mut result = Matrix::new(0, 0);
+        result.add_csg(&a, &b, &Operation::Intersection);
+        assert!(result.matrix_array.is_empty() || result.matrix_array[0].is_empty());
+        let mut result = Matrix::new(0, 0);
+        result.add_csg(&a, &b, &Operation::Union);
+        assert!((volume(&result) - 9.0).abs() < 1e-3);
+    }
+}
@@ -1,7 +1,13 @@
+use crate::clip::ClipVertex;
+use crate::gmath::{
+    add_scaled, calculate_normal, cross_product, degenerate, dot_product, normalize, signed_area,
+    subtract, triangulate,
+};
 use crate::Color;
 use crate::CurveType;
 use crate::Image;
 use crate::Matrix;
+use std::collections::HashMap;
 use std::f32;
 
 impl Image {
@@ -86,15 +92,130 @@ impl Image {
         }
     }
 
+    /// Xiaolin Wu's anti-aliased line, which shades the two pixels straddling the line
+    /// by how close the line passes to each of them
+    pub fn draw_line_aa(
+        &mut self,
+        mut x0: f32,
+        mut y0: f32,
+        mut x1: f32,
+        mut y1: f32,
+        color: Color,
+    ) {
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        // walk along x, swapping axes if the line is closer to vertical
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        let plot = |image: &mut Image, x: f32, y: f32, coverage: f32| {
+            if steep {
+                image.blend(y as i32, x as i32, color, coverage);
+            } else {
+                image.blend(x as i32, y as i32, color, coverage);
+            }
+        };
+
+        // unlike fract this stays in 0..1 for points left of or below the screen
+        let fpart = |v: f32| v - v.floor();
+
+        // first endpoint
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap = 1.0 - fpart(x0 + 0.5);
+        let x_start = x_end;
+        plot(self, x_start, y_end.floor(), (1.0 - fpart(y_end)) * x_gap);
+        plot(self, x_start, y_end.floor() + 1.0, fpart(y_end) * x_gap);
+        let mut inter_y = y_end + gradient;
+
+        // second endpoint
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = fpart(x1 + 0.5);
+        let x_stop = x_end;
+        plot(self, x_stop, y_end.floor(), (1.0 - fpart(y_end)) * x_gap);
+        plot(self, x_stop, y_end.floor() + 1.0, fpart(y_end) * x_gap);
+
+        let mut x = x_start + 1.0;
+        while x < x_stop {
+            plot(self, x, inter_y.floor(), 1.0 - fpart(inter_y));
+            plot(self, x, inter_y.floor() + 1.0, fpart(inter_y));
+            inter_y += gradient;
+            x += 1.0;
+        }
+    }
+
+    /// draws a line from the edge or polygon matrix, anti-aliased if that is turned on
+    pub fn draw_edge(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: Color) {
+        if self.antialias {
+            self.draw_line_aa(x0, y0, x1, y1, color);
+        } else {
+            self.draw_line(x0 as i32, y0 as i32, x1 as i32, y1 as i32, color);
+        }
+    }
+
     pub fn draw_lines(&mut self, matrix: &Matrix, color: Color) {
+        let mut segments = vec![];
         for i in (0..matrix.matrix_array[0].len()).step_by(2) {
-            self.draw_line(
-                matrix.matrix_array[0][i] as i32,
-                matrix.matrix_array[1][i] as i32,
-                matrix.matrix_array[0][i + 1] as i32,
-                matrix.matrix_array[1][i + 1] as i32,
-                color,
+            // skip walking pixels that would never end up on the screen
+            if let Some((x0, y0, _, x1, y1, _)) = self.clip_line(
+                matrix.matrix_array[0][i],
+                matrix.matrix_array[1][i],
+                matrix.matrix_array[2][i],
+                matrix.matrix_array[0][i + 1],
+                matrix.matrix_array[1][i + 1],
+                matrix.matrix_array[2][i + 1],
+            ) {
+                segments.push(([x0, y0], [x1, y1]));
+            }
+        }
+        self.stroke_segments(&segments, color);
+    }
+
+    /// Clears the screen and draws the edge and polygon matrices onto it, then the overlays.
+    ///
+    /// If supersample is more than 1 everything is drawn onto an image that many times
+    /// bigger first, which is then filtered down to this one.
+    pub fn render(&mut self, points: &Matrix, polygons: &Matrix, color: Color) {
+        self.clear();
+        if self.supersample > 1 {
+            let k = self.supersample as f32;
+            let mut big = Image::new(
+                self.width * self.supersample,
+                self.height * self.supersample,
             );
+            big.near = self.near;
+            big.antialias = self.antialias;
+            big.line_width = self.line_width * k;
+            big.cap = self.cap;
+            big.join = self.join;
+            // z is left alone so the near plane still lines up
+            let scale = Matrix::make_scale(k, k, 1.0);
+            let mut big_points = points.clone();
+            if !big_points.matrix_array.is_empty() {
+                big_points.multiply_matrixes(&scale);
+            }
+            let mut big_polygons = polygons.clone();
+            if !big_polygons.matrix_array.is_empty() {
+                big_polygons.multiply_matrixes(&scale);
+            }
+            big.overlays = self.overlays.iter().map(|o| o.scaled(k)).collect();
+            big.render(&big_points, &big_polygons, color);
+            self.downsample(&big);
+        } else {
+            if !points.matrix_array.is_empty() {
+                self.draw_lines(points, color);
+            }
+            if !polygons.matrix_array.is_empty() {
+                self.draw_polygons(polygons, color);
+            }
+            self.draw_overlays();
         }
     }
 
@@ -109,7 +230,8 @@ impl Image {
     ///Returns:
     ///
     ///Goes through polygons 3 points at a time, drawing
-    ///lines connecting each points to create bounding triangles
+    ///lines connecting each points to create bounding triangles.
+    ///Triangles are clipped against the near plane and the screen first
     ///====================
     pub fn draw_polygons(&mut self, polygons: &Matrix, c: Color) {
         for i in (0..polygons.matrix_array[0].len()).step_by(3) {
@@ -122,42 +244,25 @@ impl Image {
             let x2 = polygons.matrix_array[0][i + 2];
             let y2 = polygons.matrix_array[1][i + 2];
             let z2 = polygons.matrix_array[2][i + 2];
-            let ax = x1 - x0;
-            let ay = y1 - y0;
-            let az = z1 - z0;
-            let bx = x2 - x0;
-            let by = y2 - y0;
-            let bz = z2 - z0;
-            let nx = ay * bz - az * by;
-            let ny = az * bx - ax * bz;
-            let nz = ax * by - ay * bx;
-            let vx = 0.0;
-            let vy = 0.0;
-            let vz = 1.0;
-            // let dot_n_v = 1.0;
-            let dot_n_v = nx * vx + ny * vy + nz * vz;
-            if dot_n_v > 0.0 {
-                self.draw_line(
-                    polygons.matrix_array[0][i] as i32,
-                    polygons.matrix_array[1][i] as i32,
-                    polygons.matrix_array[0][i + 1] as i32,
-                    polygons.matrix_array[1][i + 1] as i32,
-                    c,
-                );
-                self.draw_line(
-                    polygons.matrix_array[0][i + 1] as i32,
-                    polygons.matrix_array[1][i + 1] as i32,
-                    polygons.matrix_array[0][i + 2] as i32,
-                    polygons.matrix_array[1][i + 2] as i32,
-                    c,
-                );
-                self.draw_line(
-                    polygons.matrix_array[0][i + 2] as i32,
-                    polygons.matrix_array[1][i + 2] as i32,
-                    polygons.matrix_array[0][i] as i32,
-                    polygons.matrix_array[1][i] as i32,
-                    c,
-                );
+            let normal = calculate_normal(polygons, i);
+            let view = [0.0, 0.0, 1.0];
+            if dot_product(&normal, &view) > 0.0 {
+                // cut off whatever is behind the near plane or off the screen, and only draw
+                // the sides that came from the triangle rather than from the cut
+                let clipped = self.clip_polygon(vec![
+                    ClipVertex::new(x0, y0, z0),
+                    ClipVertex::new(x1, y1, z1),
+                    ClipVertex::new(x2, y2, z2),
+                ]);
+                let mut segments = vec![];
+                for v in 0..clipped.len() {
+                    let start = clipped[v];
+                    let end = clipped[(v + 1) % clipped.len()];
+                    if start.edge {
+                        segments.push(([start.x, start.y], [end.x, end.y]));
+                    }
+                }
+                self.stroke_segments(&segments, c);
             }
         }
     }
@@ -202,36 +307,116 @@ impl Matrix {
         }
     }
 
-    /// x2, y2, x3, y3 are rx0, ry0, rx1, ry1 respectively if hermier
-    pub fn add_curve(
-        &mut self,
-        x0: f32,
-        y0: f32,
-        x1: f32,
-        y1: f32,
-        x2: f32,
-        y2: f32,
-        x3: f32,
-        y3: f32,
-        step: i32,
-        curve_type: &CurveType,
-    ) {
-        let matrix_x = Matrix::generate_curve_coefs(x0, x1, x2, x3, curve_type);
-        let matrix_y = Matrix::generate_curve_coefs(y0, y1, y2, y3, curve_type);
-        let mut prev_x = x0;
-        let mut prev_y = y0;
-        for t in 0..step + 1 {
-            let x = (matrix_x.matrix_array[0][0] * (t as f32 / step as f32).powi(3))
-                + (matrix_x.matrix_array[1][0] * (t as f32 / step as f32).powi(2))
-                + (matrix_x.matrix_array[2][0] * t as f32 / step as f32)
-                + matrix_x.matrix_array[3][0];
-            let y = (matrix_y.matrix_array[0][0] * (t as f32 / step as f32).powi(3))
-                + (matrix_y.matrix_array[1][0] * (t as f32 / step as f32).powi(2))
-                + (matrix_y.matrix_array[2][0] * t as f32 / step as f32)
-                + matrix_y.matrix_array[3][0];
-            self.add_edge(prev_x, prev_y, 0.0, x, y, 0.0);
-            prev_x = x;
-            prev_y = y;
+    /// points is the 4 control points as (x0, y0, z0, x1, y1, z1, ...), where
+    /// the last 2 are the slopes (rx0, ry0, rz0, rx1, ry1, rz1) if hermier
+    ///
+    /// for catmull-rom and b-splines this draws the piece between the middle two points
+    pub fn add_curve(&mut self, points: &[f32; 12], step: i32, curve_type: &CurveType) {
+        let curve = Matrix::generate_curve(points, step, curve_type);
+        let c = &curve.matrix_array;
+        for t in 1..c[0].len() {
+            self.add_edge(
+                c[0][t - 1],
+                c[1][t - 1],
+                c[2][t - 1],
+                c[0][t],
+                c[1][t],
+                c[2][t],
+            );
+        }
+    }
+
+    /// add_spline()
+    /// Inputs:   struct matrix * edges
+    ///             struct matrix * points
+    ///             int step
+    ///             enum CurveType type
+    /// Returns:
+    ///
+    /// adds a curve through any number of control points to the edge matrix.
+    /// Catmull-rom and b-splines are drawn one piece at a time with add_curve, using each
+    /// run of 4 points in a row and step lines per piece. Bezier and hermite curves take
+    /// each group of 4 points (sharing the ends) as a separate curve.
+    /// NURBS curves are drawn with step lines between each pair of knots.
+    pub fn add_spline(&mut self, points: &Matrix, step: i32, curve_type: &CurveType) {
+        let p = &points.matrix_array;
+        let count = p[0].len();
+        match curve_type {
+            CurveType::Nurbs {
+                degree,
+                weights,
+                knots,
+            } => {
+                let degree = *degree;
+                if count <= degree {
+                    panic!(
+                        "a degree {} NURBS curve needs at least {} points, not {}",
+                        degree,
+                        degree + 1,
+                        count
+                    );
+                }
+                if knots.len() != count + degree + 1 {
+                    panic!(
+                        "a degree {} NURBS curve with {} points needs {} knots, not {}",
+                        degree,
+                        count,
+                        count + degree + 1,
+                        knots.len()
+                    );
+                }
+                let mut prev: Option<[f32; 3]> = None;
+                for span in degree..count {
+                    if knots[span + 1] <= knots[span] {
+                        continue;
+                    }
+                    // every span after the first starts where the last one ended
+                    let first = if prev.is_some() { 1 } else { 0 };
+                    for t in first..step + 1 {
+                        let u =
+                            knots[span] + (knots[span + 1] - knots[span]) * t as f32 / step as f32;
+                        // de boor's algorithm on the weighted points (x * w, y * w, z * w, w)
+                        let mut d: Vec<[f32; 4]> = (0..degree + 1)
+                            .map(|j| {
+                                let i = j + span - degree;
+                                let w = weights[i];
+                                [p[0][i] * w, p[1][i] * w, p[2][i] * w, w]
+                            })
+                            .collect();
+                        for r in 1..degree + 1 {
+                            for j in (r..degree + 1).rev() {
+                                let i = j + span - degree;
+                                let alpha = (u - knots[i]) / (knots[i + degree + 1 - r] - knots[i]);
+                                let before = d[j - 1];
+                                for (axis, value) in d[j].iter_mut().enumerate() {
+                                    *value = (1.0 - alpha) * before[axis] + alpha * *value;
+                                }
+                            }
+                        }
+                        let [x, y, z, w] = d[degree];
+                        let point = [x / w, y / w, z / w];
+                        if let Some([x0, y0, z0]) = prev {
+                            self.add_edge(x0, y0, z0, point[0], point[1], point[2]);
+                        }
+                        prev = Some(point);
+                    }
+                }
+            }
+            _ => {
+                let stride = match curve_type {
+                    CurveType::Bezier | CurveType::Hermite => 3,
+                    _ => 1,
+                };
+                let mut i = 0;
+                while i + 3 < count {
+                    let mut points = [0.0; 12];
+                    for (k, value) in points.iter_mut().enumerate() {
+                        *value = p[k % 3][i + k / 3];
+                    }
+                    self.add_curve(&points, step, curve_type);
+                    i += stride;
+                }
+            }
         }
     }
 
@@ -350,50 +535,8 @@ impl Matrix {
 
     /// should call generate_sphere to create the necessary points
     pub fn add_sphere(&mut self, cx: f32, cy: f32, cz: f32, r: f32, step: i32) {
-        let lat_start: usize = 0;
-        let lat_stop = step as usize;
-        let long_start: usize = 0;
-        let long_stop = step as usize;
         let points_matrix = Matrix::generate_sphere(cx, cy, cz, r, step);
-        for lat in lat_start..lat_stop + 1 {
-            for longt in long_start..long_stop + 1 {
-                let index = lat * step as usize + longt;
-                self.add_polygon(
-                    points_matrix.matrix_array[0][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[0]
-                        [(index + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1]
-                        [(index + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2]
-                        [(index + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[0]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                );
-                self.add_polygon(
-                    points_matrix.matrix_array[0][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2][index % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[0]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2]
-                        [(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[0]
-                        [(index + step as usize) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1]
-                        [(index + step as usize) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2]
-                        [(index + step as usize) % points_matrix.matrix_array[0].len()],
-                );
-            }
-        }
+        self.add_grid(&points_matrix, step as usize, step as usize);
     }
 
     /// generate_sphere()
@@ -447,35 +590,36 @@ impl Matrix {
     /// should call generate_torus to create the necessary points
     pub fn add_torus(&mut self, cx: f32, cy: f32, cz: f32, r1: f32, r2: f32, step: i32) {
         let points_matrix = Matrix::generate_torus(cx, cy, cz, r1, r2, step);
-        let lat_start: usize = 0;
-        let lat_stop = step as usize;
-        let long_start: usize = 0;
-        let long_stop = step as usize;
-        for lat in lat_start..lat_stop + 1 {
-            for longt in long_start..long_stop + 1 {
-                let index = lat * step as usize + longt;
-                self.add_polygon(
-                    points_matrix.matrix_array[0][index],
-                    points_matrix.matrix_array[1][index],
-                    points_matrix.matrix_array[2][index],
-                    points_matrix.matrix_array[0][index + 1],
-                    points_matrix.matrix_array[1][index + 1],
-                    points_matrix.matrix_array[2][index + 1],
-                    points_matrix.matrix_array[0][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                );
-                self.add_polygon(
-                    points_matrix.matrix_array[0][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2][(index + step as usize + 1) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[0][index + 1],
-                    points_matrix.matrix_array[1][index + 1],
-                    points_matrix.matrix_array[2][index + 1],
-                    points_matrix.matrix_array[0][(index + step as usize + 2) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[1][(index + step as usize + 2) % points_matrix.matrix_array[0].len()],
-                    points_matrix.matrix_array[2][(index + step as usize + 2) % points_matrix.matrix_array[0].len()],
-                );
+        self.add_grid(&points_matrix, step as usize, step as usize);
+    }
+
+    /// add_grid()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * points
+    ///             int rows
+    ///             int cols
+    /// Returns:
+    ///
+    /// adds 2 triangles for each square of a grid of points, where points holds
+    /// rows + 1 rows of cols + 1 points each. The front of each square is the side
+    /// where going along its row and then on to the next row turns counterclockwise.
+    pub fn add_grid(&mut self, points: &Matrix, rows: usize, cols: usize) {
+        let point = |row: usize, col: usize| {
+            let index = row * (cols + 1) + col;
+            (
+                points.matrix_array[0][index],
+                points.matrix_array[1][index],
+                points.matrix_array[2][index],
+            )
+        };
+        for row in 0..rows {
+            for col in 0..cols {
+                let (ax, ay, az) = point(row, col);
+                let (bx, by, bz) = point(row, col + 1);
+                let (cx, cy, cz) = point(row + 1, col + 1);
+                let (dx, dy, dz) = point(row + 1, col);
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                self.add_polygon(ax, ay, az, cx, cy, cz, dx, dy, dz);
             }
         }
     }
@@ -530,6 +674,553 @@ impl Matrix {
         return matrix;
     }
 
+    /// add_cylinder()
+    /// Inputs:   struct matrix * polygons
+    ///             double cx
+    ///             double cy
+    ///             double cz
+    ///             double r
+    ///             double h
+    ///             int step
+    /// Returns:
+    ///
+    /// adds the triangles for a capped cylinder whose bottom is centered
+    /// at (cx, cy, cz), with radius r, running up h along the y axis,
+    /// using step slices around the outside.
+    pub fn add_cylinder(&mut self, cx: f32, cy: f32, cz: f32, r: f32, h: f32, step: i32) {
+        for slice in 0..step {
+            let (x0, z0) = Matrix::circle_point(cx, cz, r, slice, step);
+            let (x1, z1) = Matrix::circle_point(cx, cz, r, slice + 1, step);
+            // side, as two triangles going counterclockwise when seen from outside
+            self.add_polygon(x0, cy, z0, x1, cy, z1, x1, cy + h, z1);
+            self.add_polygon(x0, cy, z0, x1, cy + h, z1, x0, cy + h, z0);
+            // top cap faces up, bottom cap faces down
+            self.add_polygon(cx, cy + h, cz, x0, cy + h, z0, x1, cy + h, z1);
+            self.add_polygon(cx, cy, cz, x1, cy, z1, x0, cy, z0);
+        }
+    }
+
+    /// add_cone()
+    /// Inputs:   struct matrix * polygons
+    ///             double cx
+    ///             double cy
+    ///             double cz
+    ///             double r
+    ///             double h
+    ///             int step
+    /// Returns:
+    ///
+    /// adds the triangles for a cone whose capped base is centered
+    /// at (cx, cy, cz) with radius r, and whose tip is h above it along
+    /// the y axis, using step slices around the outside.
+    pub fn add_cone(&mut self, cx: f32, cy: f32, cz: f32, r: f32, h: f32, step: i32) {
+        for slice in 0..step {
+            let (x0, z0) = Matrix::circle_point(cx, cz, r, slice, step);
+            let (x1, z1) = Matrix::circle_point(cx, cz, r, slice + 1, step);
+            self.add_polygon(x0, cy, z0, x1, cy, z1, cx, cy + h, cz);
+            self.add_polygon(cx, cy, cz, x1, cy, z1, x0, cy, z0);
+        }
+    }
+
+    /// the point t steps of the way around a circle of radius r centered at (cx, cz) in the xz plane,
+    /// going counterclockwise when looking down from above
+    fn circle_point(cx: f32, cz: f32, r: f32, t: i32, step: i32) -> (f32, f32) {
+        let theta = 2.0 * f32::consts::PI * t as f32 / step as f32;
+        (cx + r * theta.cos(), cz - r * theta.sin())
+    }
+
+    /// add_icosphere()
+    /// Inputs:   struct matrix * polygons
+    ///             double cx
+    ///             double cy
+    ///             double cz
+    ///             double r
+    ///             int level
+    /// Returns:
+    ///
+    /// adds the triangles for a sphere with center (cx, cy, cz) and radius r,
+    /// made by splitting each face of an icosahedron into 4 level times and
+    /// pushing the new points out onto the sphere. Unlike add_sphere every
+    /// triangle comes out about the same size, with no pinching at the poles.
+    pub fn add_icosphere(&mut self, cx: f32, cy: f32, cz: f32, r: f32, level: i32) {
+        let t = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let mut points = vec![
+            [-1.0, t, 0.0],
+            [1.0, t, 0.0],
+            [-1.0, -t, 0.0],
+            [1.0, -t, 0.0],
+            [0.0, -1.0, t],
+            [0.0, 1.0, t],
+            [0.0, -1.0, -t],
+            [0.0, 1.0, -t],
+            [t, 0.0, -1.0],
+            [t, 0.0, 1.0],
+            [-t, 0.0, -1.0],
+            [-t, 0.0, 1.0],
+        ];
+        for point in points.iter_mut() {
+            normalize(point);
+        }
+        // counterclockwise when seen from outside
+        let mut faces = vec![
+            [0, 11, 5],
+            [0, 5, 1],
+            [0, 1, 7],
+            [0, 7, 10],
+            [0, 10, 11],
+            [1, 5, 9],
+            [5, 11, 4],
+            [11, 10, 2],
+            [10, 7, 6],
+            [7, 1, 8],
+            [3, 9, 4],
+            [3, 4, 2],
+            [3, 2, 6],
+            [3, 6, 8],
+            [3, 8, 9],
+            [4, 9, 5],
+            [2, 4, 11],
+            [6, 2, 10],
+            [8, 6, 7],
+            [9, 8, 1],
+        ];
+        for _ in 0..level {
+            // neighboring faces share their edge midpoints, so keep track of the ones already made
+            let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut midpoint = |a: usize, b: usize, points: &mut Vec<[f32; 3]>| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let mut point = [
+                        (points[a][0] + points[b][0]) / 2.0,
+                        (points[a][1] + points[b][1]) / 2.0,
+                        (points[a][2] + points[b][2]) / 2.0,
+                    ];
+                    normalize(&mut point);
+                    points.push(point);
+                    points.len() - 1
+                })
+            };
+            let mut new_faces = vec![];
+            for [a, b, c] in faces {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                new_faces.push([a, ab, ca]);
+                new_faces.push([b, bc, ab]);
+                new_faces.push([c, ca, bc]);
+                new_faces.push([ab, bc, ca]);
+            }
+            faces = new_faces;
+        }
+        for [a, b, c] in faces {
+            self.add_polygon(
+                cx + r * points[a][0],
+                cy + r * points[a][1],
+                cz + r * points[a][2],
+                cx + r * points[b][0],
+                cy + r * points[b][1],
+                cz + r * points[b][2],
+                cx + r * points[c][0],
+                cy + r * points[c][1],
+                cz + r * points[c][2],
+            );
+        }
+    }
+
+    /// generate_curve()
+    /// Inputs:   the same points as add_curve
+    ///
+    /// Returns: a matrix of the step + 1 points along the curve,
+    ///         from t = 0 to t = 1
+    pub fn generate_curve(points: &[f32; 12], step: i32, curve_type: &CurveType) -> Matrix {
+        let coefs = |axis: usize| {
+            Matrix::generate_curve_coefs(
+                points[axis],
+                points[axis + 3],
+                points[axis + 6],
+                points[axis + 9],
+                curve_type,
+            )
+        };
+        let (matrix_x, matrix_y, matrix_z) = (coefs(0), coefs(1), coefs(2));
+        let mut matrix = Matrix::new(0, 0);
+        for t in 0..step + 1 {
+            let t = t as f32 / step as f32;
+            matrix.add_point(
+                matrix_x.curve_value(t),
+                matrix_y.curve_value(t),
+                matrix_z.curve_value(t),
+            );
+        }
+        matrix
+    }
+
+    /// add_lathe()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * profile
+    ///             char axis
+    ///             int step
+    /// Returns:
+    ///
+    /// adds the triangles made by spinning the line through the points of
+    /// profile all the way around axis (x, y or z) in step slices.
+    /// Each profile point's x is its distance from the axis and its y is how
+    /// far along the axis it is. The outside faces out when the profile runs
+    /// in the positive direction along the axis.
+    pub fn add_lathe(&mut self, profile: &Matrix, axis: char, step: i32) {
+        let revolve = |p: usize, slice: i32| {
+            let r = profile.matrix_array[0][p];
+            let h = profile.matrix_array[1][p];
+            let theta = 2.0 * f32::consts::PI * slice as f32 / step as f32;
+            let (c, s) = (r * theta.cos(), r * theta.sin());
+            match axis {
+                'x' => (h, -s, c),
+                'z' => (-s, c, h),
+                _ => (c, h, -s),
+            }
+        };
+        for p in 0..profile.matrix_array[0].len().saturating_sub(1) {
+            for slice in 0..step {
+                let (ax, ay, az) = revolve(p, slice);
+                let (bx, by, bz) = revolve(p, slice + 1);
+                let (cx, cy, cz) = revolve(p + 1, slice + 1);
+                let (dx, dy, dz) = revolve(p + 1, slice);
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                self.add_polygon(ax, ay, az, cx, cy, cz, dx, dy, dz);
+            }
+        }
+    }
+
+    /// add_extrusion()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * outline
+    ///             double z
+    ///             double depth
+    /// Returns:
+    ///
+    /// adds the triangles for a solid made by pushing the closed outline
+    /// (using the x and y of each point) from z back to z - depth,
+    /// the same way the depth of add_box goes. Both caps are filled in
+    /// by ear clipping, so the outline can be any shape that doesn't cross itself.
+    pub fn add_extrusion(&mut self, outline: &Matrix, z: f32, depth: f32) {
+        let mut points: Vec<[f32; 2]> = vec![];
+        for i in 0..outline.matrix_array[0].len() {
+            let point = [outline.matrix_array[0][i], outline.matrix_array[1][i]];
+            // closing the outline by repeating the first point (or any point twice in a row) would leave a hole
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return;
+        }
+        if signed_area(&points) < 0.0 {
+            points.reverse();
+        }
+        let back = z - depth;
+        for [a, b, c] in triangulate(&points) {
+            // the front faces +z and the back faces -z
+            self.add_polygon(
+                points[a][0],
+                points[a][1],
+                z,
+                points[b][0],
+                points[b][1],
+                z,
+                points[c][0],
+                points[c][1],
+                z,
+            );
+            self.add_polygon(
+                points[a][0],
+                points[a][1],
+                back,
+                points[c][0],
+                points[c][1],
+                back,
+                points[b][0],
+                points[b][1],
+                back,
+            );
+        }
+        for i in 0..points.len() {
+            let [x0, y0] = points[i];
+            let [x1, y1] = points[(i + 1) % points.len()];
+            self.add_polygon(x0, y0, z, x0, y0, back, x1, y1, back);
+            self.add_polygon(x0, y0, z, x1, y1, back, x1, y1, z);
+        }
+    }
+
+    /// add_patch()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * controls
+    ///             int step
+    ///             enum CurveType type
+    /// Returns:
+    ///
+    /// adds the triangles for a bicubic surface patch, split into step by step squares.
+    /// controls holds 16 points as 4 rows of 4. For a bezier patch they are the
+    /// control points. For a hermite patch the rows and columns are both
+    /// (p0, p1, r0, r1), so the corners are points 0, 1, 4 and 5, points 2, 3, 6 and 7
+    /// are the slopes across the rows, 8, 9, 12 and 13 the slopes down the columns,
+    /// and 10, 11, 14 and 15 the twists.
+    /// The patch faces the viewer when going across a row and then down a column turns counterclockwise.
+    pub fn add_patch(&mut self, controls: &Matrix, step: i32, curve_type: &CurveType) {
+        // the coefficients of the curve across each row, for x, y and z
+        let mut rows = vec![];
+        for row in 0..4 {
+            let mut coefs = vec![];
+            for axis in 0..3 {
+                let c = &controls.matrix_array[axis];
+                coefs.push(Matrix::generate_curve_coefs(
+                    c[row * 4],
+                    c[row * 4 + 1],
+                    c[row * 4 + 2],
+                    c[row * 4 + 3],
+                    curve_type,
+                ));
+            }
+            rows.push(coefs);
+        }
+        // evaluating every row at the same u gives the control points for a curve down the patch
+        let mut grid = Matrix::new(0, 0);
+        for u in 0..step + 1 {
+            let u = u as f32 / step as f32;
+            let down: Vec<Matrix> = (0..3)
+                .map(|axis| {
+                    Matrix::generate_curve_coefs(
+                        rows[0][axis].curve_value(u),
+                        rows[1][axis].curve_value(u),
+                        rows[2][axis].curve_value(u),
+                        rows[3][axis].curve_value(u),
+                        curve_type,
+                    )
+                })
+                .collect();
+            for v in 0..step + 1 {
+                let v = v as f32 / step as f32;
+                grid.add_point(
+                    down[0].curve_value(v),
+                    down[1].curve_value(v),
+                    down[2].curve_value(v),
+                );
+            }
+        }
+        let point = |u: i32, v: i32| {
+            let index = (u * (step + 1) + v) as usize;
+            (
+                grid.matrix_array[0][index],
+                grid.matrix_array[1][index],
+                grid.matrix_array[2][index],
+            )
+        };
+        for u in 0..step {
+            for v in 0..step {
+                let (ax, ay, az) = point(u, v);
+                let (bx, by, bz) = point(u + 1, v);
+                let (cx, cy, cz) = point(u + 1, v + 1);
+                let (dx, dy, dz) = point(u, v + 1);
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                self.add_polygon(ax, ay, az, cx, cy, cz, dx, dy, dz);
+            }
+        }
+    }
+
+    /// add_sweep()
+    /// Inputs:   struct matrix * polygons
+    ///             struct matrix * profile
+    ///             struct matrix * path
+    /// Returns:
+    ///
+    /// adds the triangles for a tube made by sliding the closed cross section
+    /// profile (using the x and y of each point) along the points of path,
+    /// with both ends capped. The cross section is kept from twisting
+    /// with rotation minimizing frames, found by double reflection.
+    pub fn add_sweep(&mut self, profile: &Matrix, path: &Matrix) {
+        let mut shape: Vec<[f32; 2]> = (0..profile.matrix_array[0].len())
+            .map(|i| [profile.matrix_array[0][i], profile.matrix_array[1][i]])
+            .collect();
+        if shape.len() > 1 && shape.first() == shape.last() {
+            shape.pop();
+        }
+        if signed_area(&shape) < 0.0 {
+            shape.reverse();
+        }
+        let mut centers: Vec<[f32; 3]> = vec![];
+        for i in 0..path.matrix_array[0].len() {
+            let point = [
+                path.matrix_array[0][i],
+                path.matrix_array[1][i],
+                path.matrix_array[2][i],
+            ];
+            // repeated points have no direction to go in
+            if centers.last() != Some(&point) {
+                centers.push(point);
+            }
+        }
+        if shape.len() < 3 || centers.len() < 2 {
+            return;
+        }
+
+        let count = centers.len();
+        let tangents: Vec<[f32; 3]> = (0..count)
+            .map(|i| {
+                let mut tangent = subtract(
+                    &centers[(i + 1).min(count - 1)],
+                    &centers[i.saturating_sub(1)],
+                );
+                normalize(&mut tangent);
+                tangent
+            })
+            .collect();
+        // start with any normal that is perpendicular to the first tangent
+        let axis = if tangents[0][0].abs() < 0.9 {
+            [1.0, 0.0, 0.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        let mut normal = cross_product(&axis, &tangents[0]);
+        normalize(&mut normal);
+        let mut normals = vec![normal];
+        for i in 0..count - 1 {
+            // reflect the frame across the plane between the two centers,
+            // then again to line its tangent up with the next one
+            let v1 = subtract(&centers[i + 1], &centers[i]);
+            let c1 = dot_product(&v1, &v1);
+            let reflected_normal =
+                add_scaled(&normals[i], &v1, -2.0 / c1 * dot_product(&v1, &normals[i]));
+            let reflected_tangent = add_scaled(
+                &tangents[i],
+                &v1,
+                -2.0 / c1 * dot_product(&v1, &tangents[i]),
+            );
+            let v2 = subtract(&tangents[i + 1], &reflected_tangent);
+            let c2 = dot_product(&v2, &v2);
+            let mut next = if c2 > 0.0 {
+                add_scaled(
+                    &reflected_normal,
+                    &v2,
+                    -2.0 / c2 * dot_product(&v2, &reflected_normal),
+                )
+            } else {
+                reflected_normal
+            };
+            normalize(&mut next);
+            normals.push(next);
+        }
+
+        let ring = |i: usize, k: usize| {
+            let binormal = cross_product(&tangents[i], &normals[i]);
+            let along = add_scaled(&centers[i], &normals[i], shape[k][0]);
+            add_scaled(&along, &binormal, shape[k][1])
+        };
+        for i in 0..count - 1 {
+            for k in 0..shape.len() {
+                let next_k = (k + 1) % shape.len();
+                let [ax, ay, az] = ring(i, k);
+                let [bx, by, bz] = ring(i, next_k);
+                let [cx, cy, cz] = ring(i + 1, next_k);
+                let [dx, dy, dz] = ring(i + 1, k);
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                self.add_polygon(ax, ay, az, cx, cy, cz, dx, dy, dz);
+            }
+        }
+        // the end cap faces forwards along the path and the start cap faces back
+        for [a, b, c] in triangulate(&shape) {
+            let [ax, ay, az] = ring(count - 1, a);
+            let [bx, by, bz] = ring(count - 1, b);
+            let [cx, cy, cz] = ring(count - 1, c);
+            self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+            let [ax, ay, az] = ring(0, a);
+            let [bx, by, bz] = ring(0, b);
+            let [cx, cy, cz] = ring(0, c);
+            self.add_polygon(ax, ay, az, cx, cy, cz, bx, by, bz);
+        }
+    }
+
+    /// add_parametric()
+    /// Inputs:   struct matrix * polygons
+    ///             function surface
+    ///             double u0, u1, int ustep
+    ///             double v0, v1, int vstep
+    /// Returns:
+    ///
+    /// adds the triangles for the surface (x, y, z) = surface(u, v), with u going from u0 to u1
+    /// in ustep pieces and v going from v0 to v1 in vstep pieces.
+    /// The surface faces the viewer when going along u and then along v turns counterclockwise.
+    pub fn add_parametric(
+        &mut self,
+        surface: &dyn Fn(f32, f32) -> [f32; 3],
+        (u0, u1, ustep): (f32, f32, i32),
+        (v0, v1, vstep): (f32, f32, i32),
+    ) {
+        let points_matrix = Matrix::generate_parametric(surface, (u0, u1, ustep), (v0, v1, vstep));
+        self.add_grid(&points_matrix, vstep as usize, ustep as usize);
+    }
+
+    /// generate_parametric()
+    ///
+    /// Returns: Generates all the points along the surface of a parametric surface, as
+    /// vstep + 1 rows of ustep + 1 points. Returns a matrix of those points
+    pub fn generate_parametric(
+        surface: &dyn Fn(f32, f32) -> [f32; 3],
+        (u0, u1, ustep): (f32, f32, i32),
+        (v0, v1, vstep): (f32, f32, i32),
+    ) -> Matrix {
+        let mut points = Matrix::new(0, 0);
+        for v in 0..vstep + 1 {
+            let v = v0 + (v1 - v0) * v as f32 / vstep as f32;
+            for u in 0..ustep + 1 {
+                let u = u0 + (u1 - u0) * u as f32 / ustep as f32;
+                let [x, y, z] = surface(u, v);
+                points.add_point(x, y, z);
+            }
+        }
+        points
+    }
+
+    /// add_terrain()
+    /// Inputs:   struct matrix * polygons
+    ///             struct image * heights
+    ///             double corner[3]
+    ///             double spacing[2]
+    ///             double height
+    /// Returns:
+    ///
+    /// adds the triangles for a landscape where each pixel of heights is a point,
+    /// raised from the corner's y by height times how bright it is. The top left pixel is at
+    /// the corner's x and z, and columns go along x and rows along z, spaced spacing[0] and
+    /// spacing[1] apart. The landscape faces up along y.
+    pub fn add_terrain(
+        &mut self,
+        heights: &Image,
+        corner: [f32; 3],
+        spacing: [f32; 2],
+        height: f32,
+    ) {
+        let [x, y, z] = corner;
+        let [spacing_x, spacing_z] = spacing;
+        let point = |row: usize, col: usize| {
+            (
+                x + col as f32 * spacing_x,
+                y + heights.brightness(row, col) * height,
+                z + row as f32 * spacing_z,
+            )
+        };
+        for row in 0..heights.height.saturating_sub(1) {
+            for col in 0..heights.width.saturating_sub(1) {
+                let (ax, ay, az) = point(row, col);
+                let (bx, by, bz) = point(row + 1, col);
+                let (cx, cy, cz) = point(row + 1, col + 1);
+                let (dx, dy, dz) = point(row, col + 1);
+                self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                self.add_polygon(ax, ay, az, cx, cy, cz, dx, dy, dz);
+            }
+        }
+    }
+
     ///======== void add_polygon() ==========
     ///
     ///Inputs:   struct matrix *polygons
@@ -571,13 +1262,304 @@ impl Matrix {
         z2: f32,
     ) {
         // check for degen triangles, if it is, then don't add
-        if (x0 as i32, y0 as i32, z0 as i32) != (x1 as i32, y1 as i32, z1 as i32)
-            && (x0 as i32, y0 as i32, z0 as i32) != (x2 as i32, y2 as i32, z2 as i32)
-            && (x1 as i32, y1 as i32, z1 as i32) != (x2 as i32, y2 as i32, z2 as i32)
-        {
+        if !degenerate(&[x0, y0, z0], &[x1, y1, z1], &[x2, y2, z2]) {
             self.add_point(x0, y0, z0);
             self.add_point(x1, y1, z1);
             self.add_point(x2, y2, z2);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csg::tests::{assert_closed, volume};
+
+    /// The edges of an edge matrix as pairs of (x, y, z)
+    fn edges_of(edges: &Matrix) -> Vec<[[f32; 3]; 2]> {
+        let m = &edges.matrix_array;
+        let point = |i: usize| [m[0][i], m[1][i], m[2][i]];
+        (0..m[0].len() / 2)
+            .map(|e| [point(e * 2), point(e * 2 + 1)])
+            .collect()
+    }
+
+    fn controls(points: &[[f32; 2]]) -> Matrix {
+        let mut matrix = Matrix::new(0, 0);
+        for [x, y] in points {
+            matrix.add_point(*x, *y, 0.0);
+        }
+        matrix
+    }
+
+    fn near(a: [f32; 3], x: f32, y: f32) -> bool {
+        (a[0] - x).abs() < 1e-3 && (a[1] - y).abs() < 1e-3 && a[2] == 0.0
+    }
+
+    #[test]
+    fn catmull_rom_goes_through_the_inside_points() {
+        let points = [
+            [0.0, 0.0],
+            [10.0, 5.0],
+            [20.0, -5.0],
+            [30.0, 10.0],
+            [40.0, 0.0],
+        ];
+        let mut edges = Matrix::new(0, 0);
+        edges.add_spline(&controls(&points), 10, &CurveType::CatmullRom);
+        let edges = edges_of(&edges);
+        assert_eq!(edges.len(), 20);
+        assert!(near(edges[0][0], 10.0, 5.0));
+        assert!(near(edges[9][1], 20.0, -5.0));
+        assert!(near(edges[10][0], 20.0, -5.0));
+        assert!(near(edges[19][1], 30.0, 10.0));
+    }
+
+    #[test]
+    fn bspline_of_a_straight_line_is_straight() {
+        let points = [[0.0, 0.0], [10.0, 20.0], [20.0, 40.0], [30.0, 60.0]];
+        let mut edges = Matrix::new(0, 0);
+        edges.add_spline(&controls(&points), 10, &CurveType::BSpline);
+        let edges = edges_of(&edges);
+        assert_eq!(edges.len(), 10);
+        assert!(near(edges[0][0], 10.0, 20.0));
+        assert!(near(edges[9][1], 20.0, 40.0));
+        for [a, b] in edges {
+            assert!((a[1] - 2.0 * a[0]).abs() < 1e-3);
+            // evenly spaced points make an evenly spaced curve
+            assert!((b[0] - a[0] - 1.0).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn nurbs_spans_join_without_empty_edges() {
+        let points = [
+            [0.0, 0.0],
+            [10.0, 20.0],
+            [20.0, 0.0],
+            [30.0, 20.0],
+            [40.0, 0.0],
+        ];
+        let nurbs = CurveType::Nurbs {
+            degree: 2,
+            weights: vec![1.0; 5],
+            knots: vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 3.0, 3.0],
+        };
+        let mut edges = Matrix::new(0, 0);
+        edges.add_spline(&controls(&points), 5, &nurbs);
+        let edges = edges_of(&edges);
+        // 3 spans of 5 lines, all joined up
+        assert_eq!(edges.len(), 15);
+        assert!(near(edges[0][0], 0.0, 0.0));
+        assert!(near(edges[14][1], 40.0, 0.0));
+        for pair in edges.windows(2) {
+            assert_eq!(pair[0][1], pair[1][0]);
+            assert_ne!(pair[0][0], pair[0][1]);
+        }
+    }
+
+    #[test]
+    fn parametric_unit_sphere_has_the_right_volume() {
+        let sphere = |u: f32, v: f32| [u.sin() * v.cos(), u.sin() * v.sin(), u.cos()];
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_parametric(
+            &sphere,
+            (0.0, f32::consts::PI, 48),
+            (0.0, 2.0 * f32::consts::PI, 48),
+        );
+        assert_closed(&polygons);
+        let expected = 4.0 / 3.0 * f32::consts::PI;
+        assert!((volume(&polygons) / expected - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn grid_shapes_are_closed() {
+        let mut sphere = Matrix::new(0, 0);
+        sphere.add_sphere(0.0, 0.0, 0.0, 100.0, 20);
+        assert_closed(&sphere);
+        let expected = 4.0 / 3.0 * f32::consts::PI * 100.0_f32.powi(3);
+        assert!((volume(&sphere) / expected - 1.0).abs() < 0.05);
+
+        // the torus has always faced inward, so only check it has no holes
+        let mut torus = Matrix::new(0, 0);
+        torus.add_torus(0.0, 0.0, 0.0, 10.0, 100.0, 40);
+        assert_closed(&torus);
+    }
+
+    #[test]
+    fn terrain_has_2_triangles_a_square_facing_up() {
+        let mut heights = Image::new(4, 3);
+        heights.screen[1][2] = Color::new_color(255, 255, 255);
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_terrain(&heights, [0.0, 0.0, 0.0], [10.0, 10.0], 20.0);
+        assert_eq!(polygons.matrix_array[0].len(), 3 * 2 * 2 * 3);
+        for t in 0..12 {
+            assert!(calculate_normal(&polygons, t * 3)[1] > 0.0);
+        }
+        let highest = polygons.matrix_array[1]
+            .iter()
+            .fold(0.0_f32, |a, &b| a.max(b));
+        assert_eq!(highest, 20.0);
+    }
+
+    /// The red of the pixel at (x, y), counting y up from the bottom like plot does
+    fn red(image: &Image, x: usize, y: usize) -> i32 {
+        image.screen[image.height - 1 - y][x].r
+    }
+
+    #[test]
+    fn diagonal_aa_line_covers_one_pixel_a_column() {
+        let mut image = Image::new(12, 12);
+        image.draw_line_aa(0.0, 0.0, 10.0, 10.0, Color::new_color(255, 0, 0));
+        for x in 1..10 {
+            assert_eq!(red(&image, x, x), 255);
+            assert_eq!(red(&image, x, x + 1), 0);
+            assert_eq!(red(&image, x, x - 1), 0);
+        }
+        // the ends only cover half their pixel
+        assert_eq!(red(&image, 0, 0), 128);
+        assert_eq!(red(&image, 10, 10), 128);
+    }
+
+    #[test]
+    fn aa_line_between_two_rows_shades_both() {
+        let mut image = Image::new(12, 12);
+        image.draw_line_aa(0.0, 0.5, 10.0, 10.5, Color::new_color(255, 0, 0));
+        for x in 1..10 {
+            assert_eq!(red(&image, x, x), 128);
+            assert_eq!(red(&image, x, x + 1), 128);
+        }
+    }
+
+    #[test]
+    fn aa_line_with_ends_off_the_screen() {
+        let mut image = Image::new(10, 10);
+        // halfway between the bottom row and the one below the screen
+        image.draw_line_aa(-20.0, -0.5, 30.0, -0.5, Color::new_color(255, 0, 0));
+        for x in 0..10 {
+            assert_eq!(red(&image, x, 0), 128);
+            assert_eq!(red(&image, x, 1), 0);
+        }
+        let mut steep = Image::new(10, 10);
+        steep.draw_line_aa(-0.5, -20.0, -0.5, 30.0, Color::new_color(255, 0, 0));
+        for y in 0..10 {
+            assert_eq!(red(&steep, 0, y), 128);
+        }
+    }
+
+    /// The area of a regular polygon with step corners on a circle of radius r
+    fn polygon_area(r: f32, step: i32) -> f32 {
+        step as f32 / 2.0 * r * r * (2.0 * f32::consts::PI / step as f32).sin()
+    }
+
+    #[test]
+    fn cylinder_and_cone_are_closed_and_face_out() {
+        let mut cylinder = Matrix::new(0, 0);
+        cylinder.add_cylinder(5.0, -3.0, 2.0, 10.0, 20.0, 24);
+        assert_closed(&cylinder);
+        let expected = polygon_area(10.0, 24) * 20.0;
+        assert!((volume(&cylinder) - expected).abs() < expected * 1e-3);
+
+        let mut cone = Matrix::new(0, 0);
+        cone.add_cone(5.0, -3.0, 2.0, 10.0, 20.0, 24);
+        assert_closed(&cone);
+        let expected = polygon_area(10.0, 24) * 20.0 / 3.0;
+        assert!((volume(&cone) - expected).abs() < expected * 1e-3);
+    }
+
+    #[test]
+    fn icosphere_is_closed_and_a_little_smaller_than_the_sphere() {
+        let sphere = 4.0 / 3.0 * f32::consts::PI * 1000.0;
+        for level in 0..4 {
+            let mut icosphere = Matrix::new(0, 0);
+            icosphere.add_icosphere(5.0, -3.0, 2.0, 10.0, level);
+            let triangles = icosphere.matrix_array[0].len() / 3;
+            assert_eq!(triangles, 20 * 4_usize.pow(level as u32));
+            assert_closed(&icosphere);
+            // every point is on the sphere, so the flat faces cut a little off it
+            let v = volume(&icosphere);
+            assert!(v < sphere && v > sphere * [0.6, 0.85, 0.95, 0.98][level as usize]);
+        }
+    }
+
+    #[test]
+    fn lathe_of_a_closed_profile_is_closed_and_faces_out() {
+        // up the outside and back in across the top makes a capped cylinder
+        let profile = controls(&[[0.0, -10.0], [10.0, -10.0], [10.0, 10.0], [0.0, 10.0]]);
+        let expected = polygon_area(10.0, 24) * 20.0;
+        for axis in ['x', 'y', 'z'] {
+            let mut lathe = Matrix::new(0, 0);
+            lathe.add_lathe(&profile, axis, 24);
+            assert_closed(&lathe);
+            assert!((volume(&lathe) - expected).abs() < expected * 1e-3);
+        }
+    }
+
+    /// The controls for a flat patch covering the square from corner along u and v, as curve_type wants them
+    fn flat_patch(corner: [f32; 3], u: [f32; 3], v: [f32; 3], curve_type: &CurveType) -> Matrix {
+        let at = |a: f32, b: f32| [0, 1, 2].map(|k| corner[k] + u[k] * a + v[k] * b);
+        let mut patch = Matrix::new(0, 0);
+        for row in 0..4 {
+            for col in 0..4 {
+                let [x, y, z] = match curve_type {
+                    CurveType::Hermite => match (row, col) {
+                        // the corners, then the slopes across rows and down columns, with no twist
+                        (0 | 1, 0 | 1) => at(col as f32, row as f32),
+                        (0 | 1, _) => u,
+                        (_, 0 | 1) => v,
+                        _ => [0.0; 3],
+                    },
+                    _ => at(col as f32 / 3.0, row as f32 / 3.0),
+                };
+                patch.add_point(x, y, z);
+            }
+        }
+        patch
+    }
+
+    #[test]
+    fn box_of_patches_is_closed_and_faces_out() {
+        // each face goes across u then down v, with u cross v pointing out of the box
+        let faces = [
+            ([10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 0.0, 10.0]),
+            ([0.0, 0.0, 0.0], [0.0, 0.0, 10.0], [0.0, 10.0, 0.0]),
+            ([0.0, 10.0, 0.0], [0.0, 0.0, 10.0], [10.0, 0.0, 0.0]),
+            ([0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [0.0, 0.0, 10.0]),
+            ([0.0, 0.0, 10.0], [10.0, 0.0, 0.0], [0.0, 10.0, 0.0]),
+            ([0.0, 0.0, 0.0], [0.0, 10.0, 0.0], [10.0, 0.0, 0.0]),
+        ];
+        for curve_type in [CurveType::Bezier, CurveType::Hermite] {
+            let mut polygons = Matrix::new(0, 0);
+            for (corner, u, v) in faces {
+                polygons.add_patch(&flat_patch(corner, u, v, &curve_type), 4, &curve_type);
+            }
+            assert_closed(&polygons);
+            assert!((volume(&polygons) - 1000.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn sweep_is_closed_and_faces_out() {
+        let square = controls(&[[-2.0, -2.0], [2.0, -2.0], [2.0, 2.0], [-2.0, 2.0]]);
+        let mut straight = Matrix::new(0, 0);
+        straight.add_point(0.0, 0.0, 0.0);
+        straight.add_point(0.0, 10.0, 0.0);
+        straight.add_point(0.0, 20.0, 0.0);
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_sweep(&square, &straight);
+        assert_closed(&polygons);
+        assert!((volume(&polygons) - 320.0).abs() < 0.1);
+
+        // a quarter turn of a circle of radius 20, going around its middle
+        let mut bend = Matrix::new(0, 0);
+        for t in 0..17 {
+            let theta = f32::consts::FRAC_PI_2 * t as f32 / 16.0;
+            bend.add_point(20.0 * theta.cos(), 20.0 * theta.sin(), 0.0);
+        }
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_sweep(&square, &bend);
+        assert_closed(&polygons);
+        let expected = 16.0 * 10.0 * f32::consts::PI;
+        assert!((volume(&polygons) - expected).abs() < expected * 0.01);
+    }
+}
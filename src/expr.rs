@@ -0,0 +1,295 @@
+use std::f32;
+
+/// An arithmetic expression read from a script, such as `12.5`, `2*pi/3` or `100*cos(u)`.
+///
+/// Supports + - * / ^ (power), parentheses, unary minus, the constants pi, tau, e and inf,
+/// named variables and the functions sin, cos, tan, asin, acos, atan, atan2, sqrt, abs,
+/// exp, ln, log, floor, ceil, round, min, max and pow. Trig functions work in radians.
+pub enum Expr {
+    Number(f32),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    /// Parses text into an expression, returning a description of the problem if it is malformed.
+    pub fn parse(text: &str) -> Result<Expr, String> {
+        let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        let mut pos = 0;
+        let expr = parse_sum(&chars, &mut pos)?;
+        if pos < chars.len() {
+            return Err(format!("unexpected '{}'", chars[pos]));
+        }
+        Ok(expr)
+    }
+
+    /// Evaluates the expression, looking up variables in vars by name.
+    pub fn eval(&self, vars: &[(&str, f32)]) -> Result<f32, String> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Variable(name) => match vars.iter().find(|(var, _)| var == name) {
+                Some((_, value)) => Ok(*value),
+                None => match &name[..] {
+                    "pi" => Ok(f32::consts::PI),
+                    "tau" => Ok(f32::consts::TAU),
+                    "e" => Ok(f32::consts::E),
+                    "inf" => Ok(f32::INFINITY),
+                    _ => Err(format!("unknown variable {}", name)),
+                },
+            },
+            Expr::Negate(inner) => Ok(-inner.eval(vars)?),
+            Expr::Binary(op, left, right) => {
+                let a = left.eval(vars)?;
+                let b = right.eval(vars)?;
+                match op {
+                    '+' => Ok(a + b),
+                    '-' => Ok(a - b),
+                    '*' => Ok(a * b),
+                    '/' => Ok(a / b),
+                    _ => Ok(a.powf(b)),
+                }
+            }
+            Expr::Call(name, args) => {
+                let mut values = vec![0.0; 0];
+                for arg in args {
+                    values.push(arg.eval(vars)?);
+                }
+                call(name, &values)
+            }
+        }
+    }
+}
+
+/// Parses and evaluates an expression that has no variables other than the built in constants.
+pub fn evaluate(text: &str) -> Result<f32, String> {
+    Expr::parse(text)?.eval(&[])
+}
+
+fn call(name: &str, args: &[f32]) -> Result<f32, String> {
+    let wanted = match name {
+        "atan2" | "min" | "max" | "pow" => 2,
+        _ => 1,
+    };
+    if args.len() != wanted {
+        return Err(format!("{} takes {} argument(s)", name, wanted));
+    }
+    match name {
+        "sin" => Ok(args[0].sin()),
+        "cos" => Ok(args[0].cos()),
+        "tan" => Ok(args[0].tan()),
+        "asin" => Ok(args[0].asin()),
+        "acos" => Ok(args[0].acos()),
+        "atan" => Ok(args[0].atan()),
+        "atan2" => Ok(args[0].atan2(args[1])),
+        "sqrt" => Ok(args[0].sqrt()),
+        "abs" => Ok(args[0].abs()),
+        "exp" => Ok(args[0].exp()),
+        "ln" => Ok(args[0].ln()),
+        "log" => Ok(args[0].log10()),
+        "floor" => Ok(args[0].floor()),
+        "ceil" => Ok(args[0].ceil()),
+        "round" => Ok(args[0].round()),
+        "min" => Ok(args[0].min(args[1])),
+        "max" => Ok(args[0].max(args[1])),
+        "pow" => Ok(args[0].powf(args[1])),
+        _ => Err(format!("unknown function {}", name)),
+    }
+}
+
+// sum := product (('+' | '-') product)*
+fn parse_sum(chars: &[char], pos: &mut usize) -> Result<Expr, String> {
+    let mut left = parse_product(chars, pos)?;
+    while *pos < chars.len() && (chars[*pos] == '+' || chars[*pos] == '-') {
+        let op = chars[*pos];
+        *pos += 1;
+        let right = parse_product(chars, pos)?;
+        left = Expr::Binary(op, Box::new(left), Box::new(right));
+    }
+    Ok(left)
+}
+
+// product := unary (('*' | '/') unary)*
+fn parse_product(chars: &[char], pos: &mut usize) -> Result<Expr, String> {
+    let mut left = parse_unary(chars, pos)?;
+    while *pos < chars.len() && (chars[*pos] == '*' || chars[*pos] == '/') {
+        let op = chars[*pos];
+        *pos += 1;
+        let right = parse_unary(chars, pos)?;
+        left = Expr::Binary(op, Box::new(left), Box::new(right));
+    }
+    Ok(left)
+}
+
+// unary := ('-' | '+') unary | power
+fn parse_unary(chars: &[char], pos: &mut usize) -> Result<Expr, String> {
+    if *pos < chars.len() && chars[*pos] == '-' {
+        *pos += 1;
+        return Ok(Expr::Negate(Box::new(parse_unary(chars, pos)?)));
+    }
+    if *pos < chars.len() && chars[*pos] == '+' {
+        *pos += 1;
+        return parse_unary(chars, pos);
+    }
+    parse_power(chars, pos)
+}
+
+// power := atom ('^' unary)?, so 2^3^2 is 2^(3^2) and 2^-1 works
+fn parse_power(chars: &[char], pos: &mut usize) -> Result<Expr, String> {
+    let base = parse_atom(chars, pos)?;
+    if *pos < chars.len() && chars[*pos] == '^' {
+        *pos += 1;
+        let exponent = parse_unary(chars, pos)?;
+        return Ok(Expr::Binary('^', Box::new(base), Box::new(exponent)));
+    }
+    Ok(base)
+}
+
+// atom := number | name | name '(' sum (',' sum)* ')' | '(' sum ')'
+fn parse_atom(chars: &[char], pos: &mut usize) -> Result<Expr, String> {
+    if *pos >= chars.len() {
+        return Err("unexpected end of expression".to_string());
+    }
+    let start = *pos;
+    if chars[*pos] == '(' {
+        *pos += 1;
+        let inner = parse_sum(chars, pos)?;
+        expect(chars, pos, ')')?;
+        return Ok(inner);
+    }
+    if chars[*pos].is_ascii_digit() || chars[*pos] == '.' {
+        while *pos < chars.len() && (chars[*pos].is_ascii_digit() || chars[*pos] == '.') {
+            *pos += 1;
+        }
+        // scientific notation such as 1.5e-3
+        if *pos + 1 < chars.len()
+            && (chars[*pos] == 'e' || chars[*pos] == 'E')
+            && (chars[*pos + 1].is_ascii_digit()
+                || ((chars[*pos + 1] == '-' || chars[*pos + 1] == '+')
+                    && *pos + 2 < chars.len()
+                    && chars[*pos + 2].is_ascii_digit()))
+        {
+            *pos += 2;
+            while *pos < chars.len() && chars[*pos].is_ascii_digit() {
+                *pos += 1;
+            }
+        }
+        let text: String = chars[start..*pos].iter().collect();
+        return match text.parse() {
+            Ok(n) => Ok(Expr::Number(n)),
+            Err(_) => Err(format!("invalid number {}", text)),
+        };
+    }
+    if chars[*pos].is_alphabetic() || chars[*pos] == '_' {
+        while *pos < chars.len() && (chars[*pos].is_alphanumeric() || chars[*pos] == '_') {
+            *pos += 1;
+        }
+        let name: String = chars[start..*pos].iter().collect();
+        if *pos < chars.len() && chars[*pos] == '(' {
+            *pos += 1;
+            let mut args = vec![parse_sum(chars, pos)?];
+            while *pos < chars.len() && chars[*pos] == ',' {
+                *pos += 1;
+                args.push(parse_sum(chars, pos)?);
+            }
+            expect(chars, pos, ')')?;
+            return Ok(Expr::Call(name, args));
+        }
+        return Ok(Expr::Variable(name));
+    }
+    Err(format!("unexpected '{}'", chars[*pos]))
+}
+
+fn expect(chars: &[char], pos: &mut usize, wanted: char) -> Result<(), String> {
+    if *pos < chars.len() && chars[*pos] == wanted {
+        *pos += 1;
+        Ok(())
+    } else {
+        Err(format!("expected '{}'", wanted))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-5 * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(evaluate("12"), Ok(12.0));
+        assert_eq!(evaluate("12.5"), Ok(12.5));
+        assert_eq!(evaluate(".5"), Ok(0.5));
+        assert!(close(evaluate("1.5e-3").unwrap(), 0.0015));
+        assert!(close(evaluate("2E+2").unwrap(), 200.0));
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(evaluate("-3"), Ok(-3.0));
+        assert_eq!(evaluate("--3"), Ok(3.0));
+        assert_eq!(evaluate("2*-3"), Ok(-6.0));
+        assert_eq!(evaluate("+4"), Ok(4.0));
+        // minus applies after the power, like in math
+        assert_eq!(evaluate("-2^2"), Ok(-4.0));
+        assert_eq!(evaluate("2^-1"), Ok(0.5));
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(evaluate("1+2*3"), Ok(7.0));
+        assert_eq!(evaluate("(1+2)*3"), Ok(9.0));
+        assert_eq!(evaluate("8/2/2"), Ok(2.0));
+        assert_eq!(evaluate("10-4-3"), Ok(3.0));
+        // power goes right to left
+        assert_eq!(evaluate("2^3^2"), Ok(512.0));
+    }
+
+    #[test]
+    fn constants() {
+        assert_eq!(evaluate("pi"), Ok(f32::consts::PI));
+        assert_eq!(evaluate("tau"), Ok(f32::consts::TAU));
+        assert_eq!(evaluate("e"), Ok(f32::consts::E));
+        assert!(close(evaluate("2*pi").unwrap(), f32::consts::TAU));
+        assert_eq!(evaluate("inf"), Ok(f32::INFINITY));
+        assert_eq!(evaluate("-inf"), Ok(f32::NEG_INFINITY));
+    }
+
+    #[test]
+    fn functions() {
+        assert!(close(evaluate("100*sin(pi/2)").unwrap(), 100.0));
+        assert!(close(evaluate("sqrt(16)").unwrap(), 4.0));
+        assert_eq!(evaluate("max(2,3)"), Ok(3.0));
+        assert_eq!(evaluate("pow(2,10)"), Ok(1024.0));
+        assert!(close(
+            evaluate("atan2(1,1)").unwrap(),
+            f32::consts::FRAC_PI_4
+        ));
+    }
+
+    #[test]
+    fn variables() {
+        let expr = Expr::parse("2*u+v").unwrap();
+        assert_eq!(expr.eval(&[("u", 3.0), ("v", 1.0)]), Ok(7.0));
+        assert!(expr.eval(&[("u", 3.0)]).is_err());
+    }
+
+    #[test]
+    fn function_arity() {
+        assert!(evaluate("sin(1,2)").is_err());
+        assert!(evaluate("max(1)").is_err());
+        assert!(evaluate("nope(1)").is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        for text in [
+            "", "1+", "(1+2", "1+2)", "2**3", "1.2.3", "3x", "1,2", "foo",
+        ] {
+            assert!(evaluate(text).is_err(), "{} should not parse", text);
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::color::Color;
+use crate::image::Image;
+
+/// How many pixels wide and tall each glyph in FONT is
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// How far apart each letter and each line of text starts, in unscaled pixels,
+/// leaving a gap of 1 between letters and lines
+const ADVANCE: i32 = GLYPH_WIDTH as i32 + 1;
+const LINE_HEIGHT: i32 = GLYPH_HEIGHT as i32 + 1;
+
+/// A 5x7 pixel font for the printable ASCII characters, from space (32) to ~ (126).
+///
+/// Each glyph is 5 columns from left to right, and bit 0 of each column is its top pixel
+#[rustfmt::skip]
+const FONT: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x14, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// The glyph for c, or ? for anything FONT doesn't have
+fn glyph(c: char) -> &'static [u8; GLYPH_WIDTH] {
+    match c {
+        ' '..='~' => &FONT[c as usize - ' ' as usize],
+        _ => &FONT['?' as usize - ' ' as usize],
+    }
+}
+
+impl Image {
+    /// Draws text with FONT, where (x, y) is the bottom left corner of the first letter and each
+    /// pixel of the font becomes a scale by scale square, with scale rounded to a whole number.
+    /// Each new line in text goes under the last one
+    pub fn draw_text(&mut self, x: f32, y: f32, text: &str, scale: f32, color: Color) {
+        let size = scale.round().max(1.0) as i32;
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        for (row, line) in text.lines().enumerate() {
+            let bottom = y - row as i32 * LINE_HEIGHT * size;
+            for (col, c) in line.chars().enumerate() {
+                let left = x + col as i32 * ADVANCE * size;
+                for (gx, bits) in glyph(c).iter().enumerate() {
+                    for gy in 0..GLYPH_HEIGHT {
+                        if bits & (1 << gy) == 0 {
+                            continue;
+                        }
+                        // bit 0 is the top, but y goes up the screen
+                        let px = left + gx as i32 * size;
+                        let py = bottom + (GLYPH_HEIGHT - 1 - gy) as i32 * size;
+                        for dy in 0..size {
+                            for dx in 0..size {
+                                self.plot(px + dx, py + dy, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every lit pixel as (x, y), counting y up from the bottom like plot does
+    fn lit_pixels(text: &str, scale: f32) -> Vec<(i32, i32)> {
+        let mut image = Image::new(100, 100);
+        image.draw_text(10.0, 50.0, text, scale, Color::new_color(255, 255, 255));
+        let mut pixels = vec![];
+        for x in 0..100 {
+            for y in 0..100 {
+                if image.screen[99 - y as usize][x as usize].r != 0 {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    fn moved(pixels: &[(i32, i32)], dx: i32, dy: i32) -> Vec<(i32, i32)> {
+        pixels.iter().map(|&(x, y)| (x + dx, y + dy)).collect()
+    }
+
+    #[test]
+    fn letters_are_an_advance_apart() {
+        for scale in [1.0, 2.0] {
+            let size = scale as i32;
+            let one = lit_pixels("H", scale);
+            assert!(!one.is_empty());
+            // H fills the whole glyph, from its bottom left corner up
+            assert!(one.contains(&(10, 50)));
+            assert!(one.contains(&(10 + 5 * size - 1, 50 + 7 * size - 1)));
+            let mut two = one.clone();
+            two.extend(moved(&one, ADVANCE * size, 0));
+            two.sort();
+            assert_eq!(lit_pixels("HH", scale), two);
+        }
+    }
+
+    #[test]
+    fn new_lines_go_under() {
+        let one = lit_pixels("H", 1.0);
+        let mut two = one.clone();
+        two.extend(moved(&one, 0, -LINE_HEIGHT));
+        two.sort();
+        assert_eq!(lit_pixels("H\nH", 1.0), two);
+    }
+
+    #[test]
+    fn unknown_letters_are_question_marks() {
+        assert_eq!(lit_pixels("\u{e9}", 1.0), lit_pixels("?", 1.0));
+        assert_eq!(lit_pixels("\t", 1.0), lit_pixels("?", 1.0));
+        // a letter that takes more than one byte still only takes up one glyph
+        assert_eq!(lit_pixels("\u{e9}H", 1.0), lit_pixels("?H", 1.0));
+    }
+}
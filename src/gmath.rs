@@ -1,15 +1,198 @@
+use crate::matrix::Matrix;
+
 //vector functions
 //normalize vetor, should modify the parameter
-fn normalize() {
+pub fn normalize(vector: &mut [f32; 3]) {
+    let length = dot_product(vector, vector).sqrt();
+    if length > 0.0 {
+        for n in vector.iter_mut() {
+            *n /= length;
+        }
+    }
 }
 
 //Return the dot porduct of a . b
-fn dot_product(a, double *b ) {
-  return  0;
+pub fn dot_product(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+//Return the cross product of a x b
+pub fn cross_product(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
 }
 
+//Return a - b
+pub fn subtract(a: &[f32; 3], b: &[f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+//Return a + b * scale
+pub fn add_scaled(a: &[f32; 3], b: &[f32; 3], scale: f32) -> [f32; 3] {
+    [
+        a[0] + b[0] * scale,
+        a[1] + b[1] * scale,
+        a[2] + b[2] * scale,
+    ]
+}
 
 //Calculate the surface normal for the triangle whose first
 //point is located at index i in polygons
-fn calculate_normal(Matrix polygons, i: i32) {
-}
\ No newline at end of file
+pub fn calculate_normal(polygons: &Matrix, i: usize) -> [f32; 3] {
+    let point = |n: usize| {
+        [
+            polygons.matrix_array[0][n],
+            polygons.matrix_array[1][n],
+            polygons.matrix_array[2][n],
+        ]
+    };
+    let a = subtract(&point(i + 1), &point(i));
+    let b = subtract(&point(i + 2), &point(i));
+    cross_product(&a, &b)
+}
+
+//Return the area of the triangle abc
+pub fn triangle_area(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> f32 {
+    let normal = cross_product(&subtract(b, a), &subtract(c, a));
+    dot_product(&normal, &normal).sqrt() / 2.0
+}
+
+//Return whether the triangle abc is too thin to have a direction it faces,
+//meaning its area is tiny compared to the square of its longest side
+pub fn degenerate(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> bool {
+    let longest = [subtract(b, a), subtract(c, b), subtract(a, c)]
+        .iter()
+        .map(|side| dot_product(side, side))
+        .fold(0.0, f32::max);
+    triangle_area(a, b, c) <= 1e-6 * longest
+}
+
+//Return twice the signed area of the polygon through points,
+//positive if the points go counterclockwise
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area
+}
+
+//Split the simple polygon through points into triangles by ear clipping,
+//returning the indexes of each triangle's corners going counterclockwise
+pub fn triangulate(points: &[[f32; 2]]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if signed_area(points) < 0.0 {
+        remaining.reverse();
+    }
+    let cross = |o: usize, a: usize, b: usize| {
+        (points[a][0] - points[o][0]) * (points[b][1] - points[o][1])
+            - (points[a][1] - points[o][1]) * (points[b][0] - points[o][0])
+    };
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let mut ear = None;
+        for i in 0..n {
+            let (prev, here, next) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            // an ear has to bulge outwards and have nothing else poking into it
+            if cross(prev, here, next) <= 0.0 {
+                continue;
+            }
+            let blocked = remaining.iter().any(|&p| {
+                p != prev
+                    && p != here
+                    && p != next
+                    && cross(prev, here, p) >= 0.0
+                    && cross(here, next, p) >= 0.0
+                    && cross(next, prev, p) >= 0.0
+            });
+            if !blocked {
+                ear = Some(i);
+                break;
+            }
+        }
+        // only happens if the outline crosses itself or folds back, so just cut a corner off and keep going
+        let i = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        ]);
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csg::tests::{assert_closed, volume};
+    use crate::shapes::star_points;
+
+    /// An L with its corner at the origin, 2 wide and 3 tall, going counterclockwise
+    const L_SHAPE: [[f32; 2]; 6] = [
+        [0.0, 0.0],
+        [2.0, 0.0],
+        [2.0, 1.0],
+        [1.0, 1.0],
+        [1.0, 3.0],
+        [0.0, 3.0],
+    ];
+
+    /// Checks that triangles cover points exactly once, all going counterclockwise
+    fn check_triangles(points: &[[f32; 2]], triangles: &[[usize; 3]]) {
+        assert_eq!(triangles.len(), points.len() - 2);
+        let mut total = 0.0;
+        for &[a, b, c] in triangles {
+            let area = signed_area(&[points[a], points[b], points[c]]);
+            assert!(area > 0.0);
+            total += area;
+        }
+        assert!((total - signed_area(points).abs()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn signed_area_goes_by_direction() {
+        assert_eq!(signed_area(&L_SHAPE), 8.0);
+        let mut backwards = L_SHAPE;
+        backwards.reverse();
+        assert_eq!(signed_area(&backwards), -8.0);
+    }
+
+    #[test]
+    fn concave_outline_is_cut_into_ears() {
+        check_triangles(&L_SHAPE, &triangulate(&L_SHAPE));
+    }
+
+    #[test]
+    fn extruded_l_is_closed() {
+        let mut outline = Matrix::new(0, 0);
+        for [x, y] in L_SHAPE {
+            outline.add_point(x, y, 0.0);
+        }
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_extrusion(&outline, 0.0, 2.5);
+        assert_closed(&polygons);
+        assert!((volume(&polygons) - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn clockwise_star_comes_out_counterclockwise() {
+        let mut star = star_points(0.0, 0.0, 10.0, 4.0, 5);
+        star.reverse();
+        assert!(signed_area(&star) < 0.0);
+        check_triangles(&star, &triangulate(&star));
+    }
+}
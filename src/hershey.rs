@@ -0,0 +1,411 @@
+use crate::matrix::Matrix;
+
+/// How tall capital letters are in SIMPLEX, which sit on y = 0
+const CAP_HEIGHT: f32 = 21.0;
+
+/// How far apart lines of text are, in the same units as SIMPLEX
+const LINE_SPACING: f32 = 32.0;
+
+/// How wide the strokes of extruded text are, in the same units as SIMPLEX
+const STROKE_WIDTH: f32 = 2.0;
+
+/// How far to move along for the next letter, then the strokes that draw it,
+/// each a list of (x, y) points to draw lines between
+type Glyph = (i8, &'static [&'static [(i8, i8)]]);
+
+/// Hershey's simplex Roman font for the printable ASCII characters, from space (32) to ~ (126).
+///
+/// y goes up from the baseline, so letters like g and p go below 0
+#[rustfmt::skip]
+const SIMPLEX: [Glyph; 95] = [
+    // space
+    (16, &[]),
+    // !
+    (10, &[&[(5, 21), (5, 7)], &[(5, 2), (4, 1), (5, 0), (6, 1), (5, 2)]]),
+    // "
+    (16, &[&[(4, 21), (4, 14)], &[(12, 21), (12, 14)]]),
+    // #
+    (21, &[&[(11, 25), (4, -7)], &[(17, 25), (10, -7)], &[(4, 12), (18, 12)], &[(3, 6), (17, 6)]]),
+    // $
+    (20, &[&[(8, 25), (8, -4)], &[(12, 25), (12, -4)],
+           &[(17, 18), (15, 20), (12, 21), (8, 21), (5, 20), (3, 18), (3, 16), (4, 14), (5, 13), (7, 12), (13, 10),
+             (15, 9), (16, 8), (17, 6), (17, 3), (15, 1), (12, 0), (8, 0), (5, 1), (3, 3)]]),
+    // %
+    (24, &[&[(21, 21), (3, 0)],
+           &[(8, 21), (10, 19), (10, 17), (9, 15), (7, 14), (5, 14), (3, 16), (3, 18), (4, 20), (6, 21), (8, 21),
+             (10, 20), (13, 19), (16, 19), (19, 20), (21, 21)],
+           &[(17, 7), (15, 6), (14, 4), (14, 2), (16, 0), (18, 0), (20, 1), (21, 3), (21, 5), (19, 7), (17, 7)]]),
+    // &
+    (26, &[&[(23, 12), (23, 13), (22, 14), (21, 14), (20, 13), (19, 11), (17, 6), (15, 3), (13, 1), (11, 0), (7, 0),
+             (5, 1), (4, 2), (3, 4), (3, 6), (4, 8), (5, 9), (12, 13), (13, 14), (14, 16), (14, 18), (13, 20),
+             (11, 21), (9, 20), (8, 18), (8, 16), (9, 13), (11, 10), (16, 3), (18, 1), (20, 0), (22, 0), (23, 1),
+             (23, 2)]]),
+    // '
+    (10, &[&[(5, 19), (4, 20), (5, 21), (6, 20), (6, 18), (5, 16), (4, 15)]]),
+    // (
+    (14, &[&[(11, 25), (9, 23), (7, 20), (5, 16), (4, 11), (4, 7), (5, 2), (7, -2), (9, -5), (11, -7)]]),
+    // )
+    (14, &[&[(3, 25), (5, 23), (7, 20), (9, 16), (10, 11), (10, 7), (9, 2), (7, -2), (5, -5), (3, -7)]]),
+    // *
+    (16, &[&[(8, 21), (8, 9)], &[(3, 18), (13, 12)], &[(13, 18), (3, 12)]]),
+    // +
+    (26, &[&[(13, 18), (13, 0)], &[(4, 9), (22, 9)]]),
+    // ,
+    (10, &[&[(6, 1), (5, 0), (4, 1), (5, 2), (6, 1), (6, -1), (5, -3), (4, -4)]]),
+    // -
+    (26, &[&[(4, 9), (22, 9)]]),
+    // .
+    (10, &[&[(5, 2), (4, 1), (5, 0), (6, 1), (5, 2)]]),
+    // /
+    (22, &[&[(20, 25), (2, -7)]]),
+    // 0
+    (20, &[&[(9, 21), (6, 20), (4, 17), (3, 12), (3, 9), (4, 4), (6, 1), (9, 0), (11, 0), (14, 1), (16, 4), (17, 9),
+             (17, 12), (16, 17), (14, 20), (11, 21), (9, 21)]]),
+    // 1
+    (20, &[&[(6, 17), (8, 18), (11, 21), (11, 0)]]),
+    // 2
+    (20, &[&[(4, 16), (4, 17), (5, 19), (6, 20), (8, 21), (12, 21), (14, 20), (15, 19), (16, 17), (16, 15), (15, 13),
+             (13, 10), (3, 0), (17, 0)]]),
+    // 3
+    (20, &[&[(5, 21), (16, 21), (10, 13), (13, 13), (15, 12), (16, 11), (17, 8), (17, 6), (16, 3), (14, 1), (11, 0),
+             (8, 0), (5, 1), (4, 2), (3, 4)]]),
+    // 4
+    (20, &[&[(13, 21), (3, 7), (18, 7)], &[(13, 21), (13, 0)]]),
+    // 5
+    (20, &[&[(15, 21), (5, 21), (4, 12), (5, 13), (8, 14), (11, 14), (14, 13), (16, 11), (17, 8), (17, 6), (16, 3),
+             (14, 1), (11, 0), (8, 0), (5, 1), (4, 2), (3, 4)]]),
+    // 6
+    (20, &[&[(16, 18), (15, 20), (12, 21), (10, 21), (7, 20), (5, 17), (4, 12), (4, 7), (5, 3), (7, 1), (10, 0),
+             (11, 0), (14, 1), (16, 3), (17, 6), (17, 7), (16, 10), (14, 12), (11, 13), (10, 13), (7, 12), (5, 10),
+             (4, 7)]]),
+    // 7
+    (20, &[&[(17, 21), (7, 0)], &[(3, 21), (17, 21)]]),
+    // 8
+    (20, &[&[(8, 21), (5, 20), (4, 18), (4, 16), (5, 14), (7, 13), (11, 12), (14, 11), (16, 9), (17, 7), (17, 4),
+             (16, 2), (15, 1), (12, 0), (8, 0), (5, 1), (4, 2), (3, 4), (3, 7), (4, 9), (6, 11), (9, 12), (13, 13),
+             (15, 14), (16, 16), (16, 18), (15, 20), (12, 21), (8, 21)]]),
+    // 9
+    (20, &[&[(16, 14), (15, 11), (13, 9), (10, 8), (9, 8), (6, 9), (4, 11), (3, 14), (3, 15), (4, 18), (6, 20),
+             (9, 21), (10, 21), (13, 20), (15, 18), (16, 14), (16, 9), (15, 4), (13, 1), (10, 0), (8, 0), (5, 1),
+             (4, 3)]]),
+    // :
+    (10, &[&[(5, 14), (4, 13), (5, 12), (6, 13), (5, 14)], &[(5, 2), (4, 1), (5, 0), (6, 1), (5, 2)]]),
+    // ;
+    (10, &[&[(5, 14), (4, 13), (5, 12), (6, 13), (5, 14)],
+           &[(6, 1), (5, 0), (4, 1), (5, 2), (6, 1), (6, -1), (5, -3), (4, -4)]]),
+    // <
+    (24, &[&[(20, 18), (4, 9), (20, 0)]]),
+    // =
+    (26, &[&[(4, 12), (22, 12)], &[(4, 6), (22, 6)]]),
+    // >
+    (24, &[&[(4, 18), (20, 9), (4, 0)]]),
+    // ?
+    (18, &[&[(3, 16), (3, 17), (4, 19), (5, 20), (7, 21), (11, 21), (13, 20), (14, 19), (15, 17), (15, 15), (14, 13),
+             (13, 12), (9, 10), (9, 7)],
+           &[(9, 2), (8, 1), (9, 0), (10, 1), (9, 2)]]),
+    // @
+    (27, &[&[(18, 13), (17, 15), (15, 16), (12, 16), (10, 15), (9, 14), (8, 11), (8, 8), (9, 6), (11, 5), (14, 5),
+             (16, 6), (17, 8)],
+           &[(18, 16), (17, 8), (17, 6), (19, 5), (21, 5), (23, 7), (24, 10), (24, 12), (23, 15), (22, 17), (20, 19),
+             (18, 20), (15, 21), (12, 21), (9, 20), (7, 19), (5, 17), (4, 15), (3, 12), (3, 9), (4, 6), (5, 4), (7, 2),
+             (9, 1), (12, 0), (15, 0), (18, 1), (20, 2), (21, 3)]]),
+    // A
+    (18, &[&[(9, 21), (1, 0)], &[(9, 21), (17, 0)], &[(4, 7), (14, 7)]]),
+    // B
+    (21, &[&[(4, 21), (4, 0)],
+           &[(4, 21), (13, 21), (16, 20), (17, 19), (18, 17), (18, 15), (17, 13), (16, 12), (13, 11)],
+           &[(4, 11), (13, 11), (16, 10), (17, 9), (18, 7), (18, 4), (17, 2), (16, 1), (13, 0), (4, 0)]]),
+    // C
+    (21, &[&[(18, 16), (17, 18), (15, 20), (13, 21), (9, 21), (7, 20), (5, 18), (4, 16), (3, 13), (3, 8), (4, 5),
+             (5, 3), (7, 1), (9, 0), (13, 0), (15, 1), (17, 3), (18, 5)]]),
+    // D
+    (21, &[&[(4, 21), (4, 0)],
+           &[(4, 21), (11, 21), (14, 20), (16, 18), (17, 16), (18, 13), (18, 8), (17, 5), (16, 3), (14, 1), (11, 0),
+             (4, 0)]]),
+    // E
+    (19, &[&[(4, 21), (4, 0)], &[(4, 21), (17, 21)], &[(4, 11), (12, 11)], &[(4, 0), (17, 0)]]),
+    // F
+    (18, &[&[(4, 21), (4, 0)], &[(4, 21), (17, 21)], &[(4, 11), (12, 11)]]),
+    // G
+    (21, &[&[(18, 16), (17, 18), (15, 20), (13, 21), (9, 21), (7, 20), (5, 18), (4, 16), (3, 13), (3, 8), (4, 5),
+             (5, 3), (7, 1), (9, 0), (13, 0), (15, 1), (17, 3), (18, 5), (18, 8)],
+           &[(13, 8), (18, 8)]]),
+    // H
+    (22, &[&[(4, 21), (4, 0)], &[(18, 21), (18, 0)], &[(4, 11), (18, 11)]]),
+    // I
+    (8, &[&[(4, 21), (4, 0)]]),
+    // J
+    (16, &[&[(12, 21), (12, 5), (11, 2), (10, 1), (8, 0), (6, 0), (4, 1), (3, 2), (2, 5), (2, 7)]]),
+    // K
+    (21, &[&[(4, 21), (4, 0)], &[(18, 21), (4, 7)], &[(9, 12), (18, 0)]]),
+    // L
+    (17, &[&[(4, 21), (4, 0)], &[(4, 0), (16, 0)]]),
+    // M
+    (24, &[&[(4, 21), (4, 0)], &[(4, 21), (12, 0)], &[(20, 21), (12, 0)], &[(20, 21), (20, 0)]]),
+    // N
+    (22, &[&[(4, 21), (4, 0)], &[(4, 21), (18, 0)], &[(18, 21), (18, 0)]]),
+    // O
+    (22, &[&[(9, 21), (7, 20), (5, 18), (4, 16), (3, 13), (3, 8), (4, 5), (5, 3), (7, 1), (9, 0), (13, 0), (15, 1),
+             (17, 3), (18, 5), (19, 8), (19, 13), (18, 16), (17, 18), (15, 20), (13, 21), (9, 21)]]),
+    // P
+    (21, &[&[(4, 21), (4, 0)],
+           &[(4, 21), (13, 21), (16, 20), (17, 19), (18, 17), (18, 14), (17, 12), (16, 11), (13, 10), (4, 10)]]),
+    // Q
+    (22, &[&[(9, 21), (7, 20), (5, 18), (4, 16), (3, 13), (3, 8), (4, 5), (5, 3), (7, 1), (9, 0), (13, 0), (15, 1),
+             (17, 3), (18, 5), (19, 8), (19, 13), (18, 16), (17, 18), (15, 20), (13, 21), (9, 21)],
+           &[(12, 4), (18, -2)]]),
+    // R
+    (21, &[&[(4, 21), (4, 0)],
+           &[(4, 21), (13, 21), (16, 20), (17, 19), (18, 17), (18, 15), (17, 13), (16, 12), (13, 11), (4, 11)],
+           &[(11, 11), (18, 0)]]),
+    // S
+    (20, &[&[(17, 18), (15, 20), (12, 21), (8, 21), (5, 20), (3, 18), (3, 16), (4, 14), (5, 13), (7, 12), (13, 10),
+             (15, 9), (16, 8), (17, 6), (17, 3), (15, 1), (12, 0), (8, 0), (5, 1), (3, 3)]]),
+    // T
+    (16, &[&[(8, 21), (8, 0)], &[(1, 21), (15, 21)]]),
+    // U
+    (22, &[&[(4, 21), (4, 6), (5, 3), (7, 1), (10, 0), (12, 0), (15, 1), (17, 3), (18, 6), (18, 21)]]),
+    // V
+    (18, &[&[(1, 21), (9, 0)], &[(17, 21), (9, 0)]]),
+    // W
+    (24, &[&[(2, 21), (7, 0)], &[(12, 21), (7, 0)], &[(12, 21), (17, 0)], &[(22, 21), (17, 0)]]),
+    // X
+    (20, &[&[(3, 21), (17, 0)], &[(17, 21), (3, 0)]]),
+    // Y
+    (18, &[&[(1, 21), (9, 11), (9, 0)], &[(17, 21), (9, 11)]]),
+    // Z
+    (20, &[&[(17, 21), (3, 0)], &[(3, 21), (17, 21)], &[(3, 0), (17, 0)]]),
+    // [
+    (14, &[&[(11, 25), (4, 25), (4, -7), (11, -7)]]),
+    // backslash
+    (14, &[&[(0, 21), (14, -3)]]),
+    // ]
+    (14, &[&[(3, 25), (10, 25), (10, -7), (3, -7)]]),
+    // ^
+    (16, &[&[(2, 12), (8, 18), (14, 12)]]),
+    // _
+    (16, &[&[(0, -2), (16, -2)]]),
+    // `
+    (10, &[&[(6, 21), (5, 20), (4, 18), (4, 16), (5, 15), (6, 16), (5, 17)]]),
+    // a
+    (19, &[&[(15, 14), (15, 0)],
+           &[(15, 11), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0),
+             (13, 1), (15, 3)]]),
+    // b
+    (19, &[&[(4, 21), (4, 0)],
+           &[(4, 11), (6, 13), (8, 14), (11, 14), (13, 13), (15, 11), (16, 8), (16, 6), (15, 3), (13, 1), (11, 0),
+             (8, 0), (6, 1), (4, 3)]]),
+    // c
+    (18, &[&[(15, 11), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0),
+             (13, 1), (15, 3)]]),
+    // d
+    (19, &[&[(15, 21), (15, 0)],
+           &[(15, 11), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0),
+             (13, 1), (15, 3)]]),
+    // e
+    (18, &[&[(3, 8), (15, 8), (15, 10), (14, 12), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6),
+             (4, 3), (6, 1), (8, 0), (11, 0), (13, 1), (15, 3)]]),
+    // f
+    (12, &[&[(10, 21), (8, 21), (6, 20), (5, 17), (5, 0)], &[(2, 14), (9, 14)]]),
+    // g
+    (19, &[&[(15, 14), (15, -2), (14, -5), (13, -6), (11, -7), (8, -7), (6, -6)],
+           &[(15, 11), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0),
+             (13, 1), (15, 3)]]),
+    // h
+    (19, &[&[(4, 21), (4, 0)], &[(4, 10), (7, 13), (9, 14), (12, 14), (14, 13), (15, 10), (15, 0)]]),
+    // i
+    (8, &[&[(3, 21), (4, 20), (5, 21), (4, 22), (3, 21)], &[(4, 14), (4, 0)]]),
+    // j
+    (10, &[&[(5, 21), (6, 20), (7, 21), (6, 22), (5, 21)], &[(6, 14), (6, -3), (5, -6), (3, -7), (1, -7)]]),
+    // k
+    (17, &[&[(4, 21), (4, 0)], &[(14, 14), (4, 4)], &[(8, 8), (15, 0)]]),
+    // l
+    (8, &[&[(4, 21), (4, 0)]]),
+    // m
+    (30, &[&[(4, 14), (4, 0)], &[(4, 10), (7, 13), (9, 14), (12, 14), (14, 13), (15, 10), (15, 0)],
+           &[(15, 10), (18, 13), (20, 14), (23, 14), (25, 13), (26, 10), (26, 0)]]),
+    // n
+    (19, &[&[(4, 14), (4, 0)], &[(4, 10), (7, 13), (9, 14), (12, 14), (14, 13), (15, 10), (15, 0)]]),
+    // o
+    (19, &[&[(8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0), (13, 1), (15, 3), (16, 6),
+             (16, 8), (15, 11), (13, 13), (11, 14), (8, 14)]]),
+    // p
+    (19, &[&[(4, 14), (4, -7)],
+           &[(4, 11), (6, 13), (8, 14), (11, 14), (13, 13), (15, 11), (16, 8), (16, 6), (15, 3), (13, 1), (11, 0),
+             (8, 0), (6, 1), (4, 3)]]),
+    // q
+    (19, &[&[(15, 14), (15, -7)],
+           &[(15, 11), (13, 13), (11, 14), (8, 14), (6, 13), (4, 11), (3, 8), (3, 6), (4, 3), (6, 1), (8, 0), (11, 0),
+             (13, 1), (15, 3)]]),
+    // r
+    (13, &[&[(4, 14), (4, 0)], &[(4, 8), (5, 11), (7, 13), (9, 14), (12, 14)]]),
+    // s
+    (17, &[&[(14, 11), (13, 13), (10, 14), (7, 14), (4, 13), (3, 11), (4, 9), (6, 8), (11, 7), (13, 6), (14, 4),
+             (14, 3), (13, 1), (10, 0), (7, 0), (4, 1), (3, 3)]]),
+    // t
+    (12, &[&[(5, 21), (5, 4), (6, 1), (8, 0), (10, 0)], &[(2, 14), (9, 14)]]),
+    // u
+    (19, &[&[(4, 14), (4, 4), (5, 1), (7, 0), (10, 0), (12, 1), (15, 4)], &[(15, 14), (15, 0)]]),
+    // v
+    (16, &[&[(2, 14), (8, 0)], &[(14, 14), (8, 0)]]),
+    // w
+    (22, &[&[(3, 14), (7, 0)], &[(11, 14), (7, 0)], &[(11, 14), (15, 0)], &[(19, 14), (15, 0)]]),
+    // x
+    (17, &[&[(3, 14), (14, 0)], &[(14, 14), (3, 0)]]),
+    // y
+    (16, &[&[(2, 14), (8, 0)], &[(14, 14), (8, 0), (6, -4), (4, -6), (2, -7), (1, -7)]]),
+    // z
+    (17, &[&[(14, 14), (3, 0)], &[(3, 14), (14, 14)], &[(3, 0), (14, 0)]]),
+    // {
+    (14, &[&[(9, 25), (7, 24), (6, 22), (6, 18), (7, 16), (7, 12), (4, 9), (7, 6), (7, 2), (6, 0), (6, -4), (7, -6),
+             (9, -7)]]),
+    // |
+    (8, &[&[(4, 25), (4, -7)]]),
+    // }
+    (14, &[&[(5, 25), (7, 24), (8, 22), (8, 18), (7, 16), (7, 12), (10, 9), (7, 6), (7, 2), (8, 0), (8, -4), (7, -6),
+             (5, -7)]]),
+    // ~
+    (24, &[&[(3, 6), (3, 8), (4, 11), (6, 12), (8, 12), (10, 11), (14, 8), (16, 7), (18, 7), (20, 8), (21, 10)]]),
+];
+
+/// The glyph for c, or ? for anything SIMPLEX doesn't have
+fn glyph(c: char) -> &'static Glyph {
+    match c {
+        ' '..='~' => &SIMPLEX[c as usize - ' ' as usize],
+        _ => &SIMPLEX['?' as usize - ' ' as usize],
+    }
+}
+
+/// The strokes for text in SIMPLEX, as lists of (x, y) points, where (x, y) is the left end
+/// of the baseline of the first letter and capital letters are height tall.
+/// Each new line in text goes under the last one
+fn text_strokes(x: f32, y: f32, height: f32, text: &str) -> Vec<Vec<[f32; 2]>> {
+    let scale = height / CAP_HEIGHT;
+    let mut strokes = vec![];
+    for (row, line) in text.lines().enumerate() {
+        let baseline = y - row as f32 * LINE_SPACING * scale;
+        let mut left = x;
+        for c in line.chars() {
+            let (width, glyph_strokes) = glyph(c);
+            for stroke in glyph_strokes.iter() {
+                strokes.push(
+                    stroke
+                        .iter()
+                        .map(|&(gx, gy)| [left + gx as f32 * scale, baseline + gy as f32 * scale])
+                        .collect(),
+                );
+            }
+            left += *width as f32 * scale;
+        }
+    }
+    strokes
+}
+
+impl Matrix {
+    /// add_text()
+    /// Inputs:   struct matrix * edges
+    ///             double x
+    ///             double y
+    ///             double z
+    ///             double height
+    ///             string text
+    /// Returns:
+    ///
+    /// adds the lines that write text in Hershey's simplex font, flat at z.
+    /// (x, y) is the left end of the baseline and capital letters are height tall
+    pub fn add_text(&mut self, x: f32, y: f32, z: f32, height: f32, text: &str) {
+        for stroke in text_strokes(x, y, height, text) {
+            for pair in stroke.windows(2) {
+                self.add_edge(pair[0][0], pair[0][1], z, pair[1][0], pair[1][1], z);
+            }
+        }
+    }
+
+    /// add_text_solid()
+    /// Inputs:   struct matrix * polygons
+    ///             double x
+    ///             double y
+    ///             double z
+    ///             double height
+    ///             double depth
+    ///             string text
+    /// Returns:
+    ///
+    /// adds the triangles for text like add_text, with each line of each letter made into
+    /// a bar that goes from z back to z - depth. The bars go a little past each end of their
+    /// line so the corners where they meet are filled in
+    pub fn add_text_solid(&mut self, x: f32, y: f32, z: f32, height: f32, depth: f32, text: &str) {
+        let half = STROKE_WIDTH * height / CAP_HEIGHT / 2.0;
+        for stroke in text_strokes(x, y, height, text) {
+            for pair in stroke.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+                let length = (dx * dx + dy * dy).sqrt();
+                if length == 0.0 {
+                    continue;
+                }
+                // along is half a stroke width in the direction of the line, and side is the same to its left
+                let along = [dx / length * half, dy / length * half];
+                let side = [-along[1], along[0]];
+                let mut outline = Matrix::new(0, 0);
+                outline.add_point(a[0] - along[0] - side[0], a[1] - along[1] - side[1], 0.0);
+                outline.add_point(b[0] + along[0] - side[0], b[1] + along[1] - side[1], 0.0);
+                outline.add_point(b[0] + along[0] + side[0], b[1] + along[1] + side[1], 0.0);
+                outline.add_point(a[0] - along[0] + side[0], a[1] - along[1] + side[1], 0.0);
+                self.add_extrusion(&outline, z, depth);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csg::tests::{assert_closed, volume};
+
+    fn text(text: &str) -> Matrix {
+        let mut edges = Matrix::new(0, 0);
+        edges.add_text(10.0, 0.0, 5.0, CAP_HEIGHT, text);
+        edges
+    }
+
+    #[test]
+    fn letters_move_along_by_their_width() {
+        // l is a single line 4 in from its left, and 8 wide
+        let edges = text("ll").matrix_array;
+        assert_eq!(edges[0], vec![14.0, 14.0, 22.0, 22.0]);
+        assert_eq!(edges[1], vec![21.0, 0.0, 21.0, 0.0]);
+        assert_eq!(edges[2], vec![5.0; 4]);
+        // the same goes for letters of other widths, at any height
+        let mut wide = Matrix::new(0, 0);
+        wide.add_text(0.0, 0.0, 0.0, CAP_HEIGHT * 2.0, "ml");
+        let last_x = wide.matrix_array[0].last();
+        assert_eq!(last_x, Some(&(2.0 * (30.0 + 4.0))));
+    }
+
+    #[test]
+    fn unknown_letters_are_question_marks() {
+        assert_eq!(text("\u{e9}").matrix_array, text("?").matrix_array);
+        assert_eq!(text("\u{e9}l").matrix_array, text("?l").matrix_array);
+    }
+
+    #[test]
+    fn new_lines_go_under() {
+        let edges = text("l\nl").matrix_array;
+        assert_eq!(edges[0], vec![14.0; 4]);
+        let below = -LINE_SPACING;
+        assert_eq!(edges[1], vec![21.0, 0.0, 21.0 + below, below]);
+    }
+
+    #[test]
+    fn solid_text_is_closed_bars() {
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_text_solid(10.0, 0.0, 5.0, CAP_HEIGHT, 3.0, "ll");
+        assert_closed(&polygons);
+        // each bar is a stroke width wide and goes half of that past each end of the 21 long line
+        let bar = (21.0 + STROKE_WIDTH) * STROKE_WIDTH * 3.0;
+        assert!((volume(&polygons) - 2.0 * bar).abs() < 1e-3);
+        let z = &polygons.matrix_array[2];
+        assert!(z.iter().all(|&z| z == 5.0 || z == 2.0));
+    }
+}
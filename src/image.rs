@@ -1,4 +1,6 @@
 use crate::color::Color;
+use crate::shapes::Overlay;
+use crate::stroke::{Cap, Join};
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::fs::File;
@@ -6,10 +8,32 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::process::Command;
 
+/// how supersampled pixels are combined back down to the screen size
+#[derive(Copy, Clone)]
+pub enum Filter {
+    /// average of the samples inside the pixel
+    Box,
+    /// weighted average of the samples within a pixel of the center, favoring the closest
+    Tent,
+}
+
 pub struct Image {
     pub screen: Vec<Vec<Color>>,
     pub height: usize,
     pub width: usize,
+    /// anything with a z greater than this is behind the viewer and gets clipped away
+    pub near: f32,
+    /// draw lines with Wu's anti-aliasing instead of Bresenham, which only lines 1 pixel wide get
+    pub antialias: bool,
+    /// render at this many times the width and height, then filter down
+    pub supersample: usize,
+    pub filter: Filter,
+    /// flat shapes drawn on top of everything else when rendering
+    pub overlays: Vec<Overlay>,
+    /// how many pixels wide lines are
+    pub line_width: f32,
+    pub cap: Cap,
+    pub join: Join,
 }
 
 impl Image {
@@ -18,6 +42,14 @@ impl Image {
             screen: vec![vec![Color::new(); image_width]; image_height],
             width: image_width,
             height: image_height,
+            near: f32::INFINITY,
+            antialias: false,
+            supersample: 1,
+            filter: Filter::Box,
+            overlays: vec![],
+            line_width: 1.0,
+            cap: Cap::Butt,
+            join: Join::Miter,
         }
     }
 
@@ -27,6 +59,55 @@ impl Image {
         }
     }
 
+    /// mixes color into the pixel at (x, y), where coverage goes from 0 (leave the pixel alone) to 1 (same as plot)
+    pub fn blend(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            let pixel = &mut self.screen[(self.height - 1) - y as usize][x as usize];
+            let coverage = coverage.clamp(0.0, 1.0);
+            pixel.r += ((color.r - pixel.r) as f32 * coverage).round() as i32;
+            pixel.g += ((color.g - pixel.g) as f32 * coverage).round() as i32;
+            pixel.b += ((color.b - pixel.b) as f32 * coverage).round() as i32;
+        }
+    }
+
+    /// Shrinks big, which must be supersample times the size of this image, down onto this image
+    pub fn downsample(&mut self, big: &Image) {
+        let k = self.supersample;
+        // the box filter looks at the k by k samples in the pixel, the tent filter spreads out a further k/2 on each side
+        let (reach, weight): (usize, fn(f32, f32) -> f32) = match self.filter {
+            Filter::Box => (0, |_, _| 1.0),
+            Filter::Tent => (k / 2, |d, k| (1.0 - d.abs() / k).max(0.0)),
+        };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let center_row = ((row * k) as f32) + k as f32 / 2.0;
+                let center_col = ((col * k) as f32) + k as f32 / 2.0;
+                let mut total = [0.0; 3];
+                let mut total_weight = 0.0;
+                for big_row in
+                    (row * k).saturating_sub(reach)..((row + 1) * k + reach).min(big.height)
+                {
+                    let row_weight = weight(big_row as f32 + 0.5 - center_row, k as f32);
+                    for big_col in
+                        (col * k).saturating_sub(reach)..((col + 1) * k + reach).min(big.width)
+                    {
+                        let w = row_weight * weight(big_col as f32 + 0.5 - center_col, k as f32);
+                        let sample = big.screen[big_row][big_col];
+                        total[0] += sample.r as f32 * w;
+                        total[1] += sample.g as f32 * w;
+                        total[2] += sample.b as f32 * w;
+                        total_weight += w;
+                    }
+                }
+                self.screen[row][col] = Color::new_color(
+                    (total[0] / total_weight).round() as i32,
+                    (total[1] / total_weight).round() as i32,
+                    (total[2] / total_weight).round() as i32,
+                );
+            }
+        }
+    }
+
     fn create_data(&self) -> String {
         let mut result: String =
             format!("P3\n{} {}\n255\n", self.screen[0].len(), self.screen.len());
@@ -57,6 +138,90 @@ impl Image {
         };
     }
 
+    /// Reads a PPM or PGM image, in either the plain text (P3, P2) or binary (P6, P5) format
+    pub fn read_file(file_name: &str) -> Image {
+        let data = match std::fs::read(file_name) {
+            Err(error) => panic!("failed to read image file {} because {}", file_name, error),
+            Ok(data) => data,
+        };
+        // the header is the magic number, width, height and max value, with # starting a comment
+        let mut pos = 0;
+        let mut header = vec![];
+        while header.len() < 4 {
+            while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+                if data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                panic!("image file {} ends before its header does", file_name);
+            }
+            header.push(String::from_utf8_lossy(&data[start..pos]).to_string());
+        }
+        let channels = match &header[0][..] {
+            "P2" | "P5" => 1,
+            "P3" | "P6" => 3,
+            magic => panic!("image file {} is {}, not a PPM or PGM", file_name, magic),
+        };
+        let number = |text: &str| match text.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => panic!("image file {} has a bad header value {}", file_name, text),
+        };
+        let (width, height, max) = (number(&header[1]), number(&header[2]), number(&header[3]));
+        let count = width * height * channels;
+        let values: Vec<usize> = if header[0] == "P5" || header[0] == "P6" {
+            // a single whitespace character separates the header from the bytes
+            let bytes = &data[(pos + 1).min(data.len())..];
+            let size = if max > 255 { 2 } else { 1 };
+            if bytes.len() < count * size {
+                panic!("image file {} is missing pixels", file_name);
+            }
+            bytes
+                .chunks_exact(size)
+                .take(count)
+                .map(|b| b.iter().fold(0, |n, &byte| n * 256 + byte as usize))
+                .collect()
+        } else {
+            let values: Vec<usize> = String::from_utf8_lossy(&data[pos..])
+                .split_whitespace()
+                .take(count)
+                .map(number)
+                .collect();
+            if values.len() < count {
+                panic!("image file {} is missing pixels", file_name);
+            }
+            values
+        };
+
+        let mut image = Image::new(width, height);
+        let scale = |value: usize| (value * 255 / max.max(1)) as i32;
+        for row in 0..height {
+            for col in 0..width {
+                let pixel = &values[(row * width + col) * channels..][..channels];
+                image.screen[row][col] = if channels == 1 {
+                    Color::new_color(scale(pixel[0]), scale(pixel[0]), scale(pixel[0]))
+                } else {
+                    Color::new_color(scale(pixel[0]), scale(pixel[1]), scale(pixel[2]))
+                };
+            }
+        }
+        image
+    }
+
+    /// how bright the pixel in the given row (counting from the top) and column is, from 0 to 1
+    pub fn brightness(&self, row: usize, col: usize) -> f32 {
+        let pixel = self.screen[row][col];
+        (pixel.r + pixel.g + pixel.b) as f32 / (3.0 * 255.0)
+    }
+
     pub fn clear(&mut self) {
         for i in 0..self.screen.len() {
             for v in 0..self.screen[0].len() {
@@ -79,3 +244,140 @@ impl Image {
             .expect("failed to open image");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    /// Reads data as an image file
+    fn read(name: &str, data: &[u8]) -> Image {
+        let path = env::temp_dir().join(format!("image_test_{}_{}", process::id(), name));
+        fs::write(&path, data).unwrap();
+        let image = Image::read_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        image
+    }
+
+    fn rgb(image: &Image, row: usize, col: usize) -> (i32, i32, i32) {
+        let pixel = image.screen[row][col];
+        (pixel.r, pixel.g, pixel.b)
+    }
+
+    #[test]
+    fn plain_ppm() {
+        let image = read("p3", b"P3\n2 1\n255\n255 0 0  0 128 255\n");
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(rgb(&image, 0, 0), (255, 0, 0));
+        assert_eq!(rgb(&image, 0, 1), (0, 128, 255));
+    }
+
+    #[test]
+    fn comments_and_a_small_max_value() {
+        let image = read(
+            "p2",
+            b"P2 # gray\n# made by hand\n2 # wide\n2\n15\n0 15\n5 10\n",
+        );
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(rgb(&image, 0, 1), (255, 255, 255));
+        assert_eq!(rgb(&image, 1, 0), (85, 85, 85));
+        assert_eq!(rgb(&image, 1, 1), (170, 170, 170));
+    }
+
+    #[test]
+    fn binary_pgm() {
+        let image = read("p5", b"P5\n3 1\n255\n\x00\x80\xff");
+        assert_eq!(rgb(&image, 0, 0), (0, 0, 0));
+        assert_eq!(rgb(&image, 0, 1), (128, 128, 128));
+        assert_eq!(image.brightness(0, 2), 1.0);
+    }
+
+    #[test]
+    fn binary_ppm_with_2_bytes_a_value() {
+        let image = read("p6", b"P6\n1 1\n65535\n\xff\xff\x80\x00\x00\x00");
+        assert_eq!(rgb(&image, 0, 0), (255, 127, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "is missing pixels")]
+    fn plain_image_cut_short() {
+        read("p3_short", b"P3\n2 2\n255\n1 2 3 4 5 6\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "is missing pixels")]
+    fn binary_image_cut_short() {
+        read("p6_short", b"P6\n2 1\n255\n\x01\x02\x03\x04");
+    }
+
+    #[test]
+    #[should_panic(expected = "ends before its header does")]
+    fn header_cut_short() {
+        read("header_short", b"P2\n4 4\n");
+    }
+
+    #[test]
+    fn blend_mixes_by_coverage() {
+        let mut image = Image::new(3, 1);
+        let white = Color::new_color(255, 255, 255);
+        image.blend(0, 0, white, 0.25);
+        image.blend(1, 0, white, 2.0);
+        image.blend(2, 0, white, -1.0);
+        // off the screen is ignored
+        image.blend(-1, 0, white, 1.0);
+        image.blend(0, 5, white, 1.0);
+        assert_eq!(rgb(&image, 0, 0), (64, 64, 64));
+        assert_eq!(rgb(&image, 0, 1), (255, 255, 255));
+        assert_eq!(rgb(&image, 0, 2), (0, 0, 0));
+        image.blend(0, 0, Color::new_color(0, 0, 0), 0.5);
+        assert_eq!(rgb(&image, 0, 0), (32, 32, 32));
+    }
+
+    /// A 2 by 2 image supersampled k times with the given filter, and a big image to go with it
+    fn supersampled(k: usize, filter: Filter) -> (Image, Image) {
+        let mut small = Image::new(2, 2);
+        small.supersample = k;
+        small.filter = filter;
+        (small, Image::new(2 * k, 2 * k))
+    }
+
+    #[test]
+    fn filters_keep_a_flat_color() {
+        for filter in [Filter::Box, Filter::Tent] {
+            let (mut small, mut big) = supersampled(4, filter);
+            for row in big.screen.iter_mut() {
+                row.fill(Color::new_color(100, 150, 200));
+            }
+            small.downsample(&big);
+            for row in 0..2 {
+                for col in 0..2 {
+                    assert_eq!(rgb(&small, row, col), (100, 150, 200));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn box_filter_keeps_a_sample_in_its_pixel() {
+        let (mut small, mut big) = supersampled(2, Filter::Box);
+        big.screen[3][3] = Color::new_color(255, 255, 255);
+        small.downsample(&big);
+        assert_eq!(rgb(&small, 1, 1), (64, 64, 64));
+        assert_eq!(rgb(&small, 0, 0), (0, 0, 0));
+        assert_eq!(rgb(&small, 0, 1), (0, 0, 0));
+        assert_eq!(rgb(&small, 1, 0), (0, 0, 0));
+    }
+
+    #[test]
+    fn tent_filter_spreads_a_sample_by_distance() {
+        let (mut small, mut big) = supersampled(4, Filter::Tent);
+        big.screen[3][3] = Color::new_color(255, 255, 255);
+        small.downsample(&big);
+        // the sample is 1.5 from the middle of pixel (0, 0) on each axis and 2.5 from pixel 1,
+        // for weights of 0.625 and 0.375, out of 3.5 a side in total
+        assert_eq!(rgb(&small, 0, 0).0, 8);
+        assert_eq!(rgb(&small, 0, 1).0, 5);
+        assert_eq!(rgb(&small, 1, 0).0, 5);
+        assert_eq!(rgb(&small, 1, 1).0, 3);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::matrix::Matrix;
+
+/// The 6 faces of a cell, as its corners going counterclockwise when seen from outside the cell.
+///
+/// Corner c is at (c & 1, (c >> 1) & 1, (c >> 2) & 1) cells from the cell's lowest corner
+const FACES: [[usize; 4]; 6] = [
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+];
+
+impl Matrix {
+    /// add_isosurface()
+    /// Inputs:   struct matrix * polygons
+    ///             function field
+    ///             double min[3], max[3]
+    ///             int res
+    /// Returns:
+    ///
+    /// adds the triangles for the surface where field(x, y, z) is 0, found with marching cubes
+    /// over the box from min to max split into res cells along each side.
+    /// Places where field is negative are inside, and the triangles face out of them.
+    /// An infinite value counts as the biggest finite one.
+    ///
+    /// Instead of the usual table of 256 cases, each cell works out its triangles from its faces:
+    /// on every face the surface crosses, the crossings are joined up so that inside corners
+    /// which only touch diagonally are cut off separately, and the joins are then followed
+    /// around the cell into loops.
+    /// Neighboring cells always make the same choice on the face they share, so there are no holes.
+    pub fn add_isosurface(
+        &mut self,
+        field: &dyn Fn(f32, f32, f32) -> f32,
+        min: [f32; 3],
+        max: [f32; 3],
+        res: usize,
+    ) {
+        let res = res.max(1);
+        let size = [
+            (max[0] - min[0]) / res as f32,
+            (max[1] - min[1]) / res as f32,
+            (max[2] - min[2]) / res as f32,
+        ];
+        let position = |i: usize, j: usize, k: usize| {
+            [
+                min[0] + i as f32 * size[0],
+                min[1] + j as f32 * size[1],
+                min[2] + k as f32 * size[2],
+            ]
+        };
+        let index = |i: usize, j: usize, k: usize| (k * (res + 1) + j) * (res + 1) + i;
+        let mut values = vec![0.0; (res + 1) * (res + 1) * (res + 1)];
+        for k in 0..res + 1 {
+            for j in 0..res + 1 {
+                for i in 0..res + 1 {
+                    let [x, y, z] = position(i, j, k);
+                    // an infinite value would make the crossings on its edges NaN
+                    values[index(i, j, k)] = field(x, y, z).clamp(-f32::MAX, f32::MAX);
+                }
+            }
+        }
+
+        for k in 0..res {
+            for j in 0..res {
+                for i in 0..res {
+                    let corner = |c: usize| (i + (c & 1), j + ((c >> 1) & 1), k + ((c >> 2) & 1));
+                    let value = |c: usize| {
+                        let (i, j, k) = corner(c);
+                        values[index(i, j, k)]
+                    };
+                    let inside: Vec<bool> = (0..8).map(|c| value(c) < 0.0).collect();
+                    if inside.iter().all(|&b| b) || inside.iter().all(|&b| !b) {
+                        continue;
+                    }
+
+                    // next[a * 8 + b] is the edge that the join starting on edge (a, b) ends on
+                    let mut next = [None; 64];
+                    for face in FACES.iter() {
+                        let mut into = vec![];
+                        let mut out_of = vec![];
+                        for m in 0..4 {
+                            let (a, b) = (face[m], face[(m + 1) % 4]);
+                            if !inside[a] && inside[b] {
+                                into.push(m);
+                            } else if inside[a] && !inside[b] {
+                                out_of.push(m);
+                            }
+                        }
+                        // each crossing into the inside is joined to the next crossing back out,
+                        // going counterclockwise, so when two opposite corners are inside
+                        // they get cut off separately
+                        for &m in &into {
+                            let end = (1..4)
+                                .map(|d| (m + d) % 4)
+                                .find(|n| out_of.contains(n))
+                                .unwrap();
+                            let start_edge = edge_key(face[m], face[(m + 1) % 4]);
+                            let end_edge = edge_key(face[end], face[(end + 1) % 4]);
+                            next[start_edge] = Some(end_edge);
+                        }
+                    }
+
+                    // the crossing point on an edge, always worked out from the lower corner
+                    // so the cell next door gets exactly the same point
+                    let crossing = |key: usize| {
+                        let (a, b) = (key / 8, key % 8);
+                        let (fa, fb) = (value(a), value(b));
+                        let t = fa / (fa - fb);
+                        let (ai, aj, ak) = corner(a);
+                        let (bi, bj, bk) = corner(b);
+                        let pa = position(ai, aj, ak);
+                        let pb = position(bi, bj, bk);
+                        [
+                            pa[0] + (pb[0] - pa[0]) * t,
+                            pa[1] + (pb[1] - pa[1]) * t,
+                            pa[2] + (pb[2] - pa[2]) * t,
+                        ]
+                    };
+                    for start in 0..64 {
+                        if next[start].is_none() {
+                            continue;
+                        }
+                        let mut ring = vec![];
+                        let mut key = start;
+                        while let Some(following) = next[key].take() {
+                            ring.push(crossing(key));
+                            key = following;
+                        }
+                        for p in 1..ring.len().saturating_sub(1) {
+                            let [ax, ay, az] = ring[0];
+                            let [bx, by, bz] = ring[p];
+                            let [cx, cy, cz] = ring[p + 1];
+                            self.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A number for the edge between corners a and b of a cell that is the same either way around
+fn edge_key(a: usize, b: usize) -> usize {
+    a.min(b) * 8 + a.max(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csg::tests::{assert_closed, volume};
+    use crate::matrix::Matrix;
+    use std::f32;
+
+    /// Every point of polygons as (x, y, z)
+    fn points_of(polygons: &Matrix) -> Vec<[f32; 3]> {
+        let m = &polygons.matrix_array;
+        (0..m[0].len())
+            .map(|i| [m[0][i], m[1][i], m[2][i]])
+            .collect()
+    }
+
+    #[test]
+    fn sphere_is_closed_and_the_right_size() {
+        let mut polygons = Matrix::new(0, 0);
+        let sphere = |x: f32, y: f32, z: f32| x * x + y * y + z * z - 100.0;
+        polygons.add_isosurface(&sphere, [-12.0; 3], [12.0; 3], 24);
+        assert_closed(&polygons);
+        let expected = 4.0 / 3.0 * f32::consts::PI * 1000.0;
+        assert!((volume(&polygons) / expected - 1.0).abs() < 0.02);
+        for [x, y, z] in points_of(&polygons) {
+            assert!(((x * x + y * y + z * z).sqrt() - 10.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn infinite_values_do_not_make_nan_points() {
+        let mut polygons = Matrix::new(0, 0);
+        // the grid has a corner right on the center
+        let ball = |x: f32, y: f32, z: f32| 1.0 - 2500.0 / (x * x + y * y + z * z);
+        polygons.add_isosurface(&ball, [-50.0; 3], [50.0; 3], 2);
+        assert_closed(&polygons);
+        let points = points_of(&polygons);
+        assert!(points.iter().flatten().all(|n| n.is_finite()));
+    }
+}
@@ -4,11 +4,24 @@ use matrix::CurveType;
 use matrix::Matrix;
 use parser::parse_file;
 use std::env;
+mod clip;
 mod color;
+mod csg;
 mod draw;
+mod expr;
+mod font;
+mod gmath;
+mod hershey;
 mod image;
+mod isosurface;
 mod matrix;
+mod mesh;
 mod parser;
+mod shapes;
+mod simplify;
+mod stroke;
+mod subdivide;
+mod teapot;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -17,23 +30,38 @@ fn main() {
     let mut edges = Matrix::new(0, 0);
     let mut polygons = Matrix::new(0, 0);
     let mut transform = Matrix::new(4, 4);
-    if args.len() > 1 && args[1] == "art" {
-        parse_file(
-            "macprowheels",
-            &mut edges,
-            &mut polygons,
-            &mut transform,
-            &mut screen,
-            color,
-        );
-    } else {
-        parse_file(
-            "script",
-            &mut edges,
-            &mut polygons,
-            &mut transform,
-            &mut screen,
-            color,
-        );
+    let mut file = "script";
+    let mut i = 1;
+    while i < args.len() {
+        match &*args[i] {
+            "art" => {
+                file = "macprowheels";
+            }
+            "teapot" => {
+                file = "teapot_script";
+            }
+            "-s" | "--supersample" => {
+                i += 1;
+                let usage = "supersample takes a whole number, such as --supersample 4";
+                let n: usize = args.get(i).expect(usage).parse().expect(usage);
+                // 0 would make an empty screen, so it means no supersampling like 1 does
+                screen.supersample = n.max(1);
+            }
+            _ => {
+                panic!(
+                    "Invalid argument {}: please use art, teapot or --supersample N.",
+                    args[i]
+                );
+            }
+        }
+        i += 1;
     }
+    parse_file(
+        file,
+        &mut edges,
+        &mut polygons,
+        &mut transform,
+        &mut screen,
+        color,
+    );
 }
@@ -1,13 +1,41 @@
 use std::fmt;
 use std::f32;
 
+/// Scales smaller than this are treated as 0 by decompose
+const ZERO_SCALE: f32 = 1e-6;
+
+#[derive(Clone)]
 pub struct Matrix{
     pub(in crate) matrix_array: Vec<Vec<f32>>,
 }
 
+/// The parts that a 4x4 transformation matrix is built out of,
+/// in the order scale, then shear, then rotate, then translate
+pub struct Decomposition{
+    pub translation: [f32; 3],
+    /// rotations about x, then y, then z in degrees
+    pub rotation: [f32; 3],
+    /// (w, x, y, z)
+    pub quaternion: [f32; 4],
+    pub scale: [f32; 3],
+    /// xy, xz and yz shear factors
+    pub shear: [f32; 3],
+}
+
 pub enum CurveType{
     Bezier,
-    Hermite
+    Hermite,
+    /// passes through every control point except the first and last
+    CatmullRom,
+    /// uniform cubic b-spline, smoother than catmull-rom but doesn't pass through the points
+    BSpline,
+    /// rational b-spline of any degree, with a weight for each control point
+    /// and a knot vector that has (number of points + degree + 1) knots
+    Nurbs{
+        degree: usize,
+        weights: Vec<f32>,
+        knots: Vec<f32>,
+    },
 }
 
 impl Matrix{
@@ -47,12 +75,12 @@ impl Matrix{
         println!("{}", self);
     }
     
-    pub fn make_translate(x: i32, y: i32, z: i32) -> Matrix{
+    pub fn make_translate(x: f32, y: f32, z: f32) -> Matrix{
         let mut matrix = Matrix::new(4, 4);
         matrix.identity();
-        matrix.matrix_array[0][3] = x as f32;
-        matrix.matrix_array[1][3] = y as f32;
-        matrix.matrix_array[2][3] = z as f32;
+        matrix.matrix_array[0][3] = x;
+        matrix.matrix_array[1][3] = y;
+        matrix.matrix_array[2][3] = z;
         return matrix;
     }
     
@@ -98,6 +126,132 @@ impl Matrix{
         return matrix;
     }
     
+    /// Splits a 4x4 transformation matrix into its translation, rotation, scale and shear
+    ///
+    /// The upper 3x3 is split with Gram-Schmidt, so it comes back as
+    /// rotation * shear * scale. A negative determinant (a mirror) is shown as a negative scale
+    /// on one axis, and an axis that is squashed flat gets a scale and shears of 0.
+    /// Any perspective in the bottom row is ignored.
+    pub fn decompose(&self) -> Decomposition{
+        let m = &self.matrix_array;
+        let translation = [m[0][3], m[1][3], m[2][3]];
+        let mut cols = [[0.0; 3]; 3];
+        for (c, col) in cols.iter_mut().enumerate(){
+            for r in 0..3{
+                col[r] = m[r][c];
+            }
+        }
+        let dot = |a: &[f32; 3], b: &[f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let mut scale = [0.0; 3];
+        let mut shear = [0.0; 3];
+
+        // a column that is (about) squashed flat keeps a scale of 0 and is filled in afterwards
+        let mut normalize = |cols: &mut [[f32; 3]; 3], k: usize|{
+            scale[k] = dot(&cols[k], &cols[k]).sqrt();
+            if scale[k] < ZERO_SCALE{
+                scale[k] = 0.0;
+                cols[k] = [0.0; 3];
+            }else{
+                cols[k] = cols[k].map(|n| n / scale[k]);
+            }
+        };
+
+        normalize(&mut cols, 0);
+
+        shear[0] = dot(&cols[0], &cols[1]);
+        cols[1] = [0, 1, 2].map(|r| cols[1][r] - shear[0] * cols[0][r]);
+        normalize(&mut cols, 1);
+
+        shear[1] = dot(&cols[0], &cols[2]);
+        cols[2] = [0, 1, 2].map(|r| cols[2][r] - shear[1] * cols[0][r]);
+        shear[2] = dot(&cols[1], &cols[2]);
+        cols[2] = [0, 1, 2].map(|r| cols[2][r] - shear[2] * cols[1][r]);
+        normalize(&mut cols, 2);
+
+        for (k, s) in [(1, 0), (2, 1), (2, 2)]{
+            shear[s] = if scale[k] == 0.0{ 0.0 }else{ shear[s] / scale[k] };
+        }
+
+        // fill in the rotation for flat columns so it is still a rotation
+        let cross = |a: &[f32; 3], b: &[f32; 3]| [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ];
+        let flat: Vec<usize> = (0..3).filter(|&k| scale[k] == 0.0).collect();
+        match flat.len(){
+            3 => {
+                cols = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+            }
+            2 => {
+                // start the next column from whichever axis is furthest from the one left
+                let k = (0..3).find(|k| !flat.contains(k)).unwrap();
+                let axis = (0..3)
+                    .min_by(|&a, &b| cols[k][a].abs().partial_cmp(&cols[k][b].abs()).unwrap())
+                    .unwrap();
+                let along = cols[k][axis];
+                let mut next = [0, 1, 2].map(|r| -along * cols[k][r]);
+                next[axis] += 1.0;
+                let length = dot(&next, &next).sqrt();
+                cols[(k + 1) % 3] = next.map(|n| n / length);
+                cols[(k + 2) % 3] = cross(&cols[k], &cols[(k + 1) % 3]);
+            }
+            1 => {
+                let k = flat[0];
+                cols[k] = cross(&cols[(k + 1) % 3], &cols[(k + 2) % 3]);
+            }
+            _ => {}
+        }
+
+        // a mirror shows up as one negative scale, on the axis that leaves the least rotation
+        if dot(&cols[0], &cross(&cols[1], &cols[2])) < 0.0{
+            let k = (0..3)
+                .min_by(|&a, &b| cols[a][a].partial_cmp(&cols[b][b]).unwrap())
+                .unwrap();
+            scale[k] *= -1.0;
+            cols[k] = cols[k].map(|n| -n);
+            // the shears between the flipped axis and the others flip with it
+            for (s, (a, b)) in [(0, (0, 1)), (1, (0, 2)), (2, (1, 2))]{
+                if a == k || b == k{
+                    shear[s] *= -1.0;
+                }
+            }
+        }
+
+        // r[row][col] of the pure rotation, which is Rz * Ry * Rx
+        let r = |row: usize, col: usize| cols[col][row];
+        let rotation_y = (-r(2, 0)).clamp(-1.0, 1.0).asin();
+        let (rotation_x, rotation_z) = if rotation_y.cos().abs() > 1e-6{
+            (r(2, 1).atan2(r(2, 2)), r(1, 0).atan2(r(0, 0)))
+        }else{
+            // gimbal lock, so put all of the spin in x
+            ((-r(1, 2)).atan2(r(1, 1)), 0.0)
+        };
+
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let quaternion = if trace > 0.0{
+            let s = (trace + 1.0).sqrt() * 2.0;
+            [0.25 * s, (r(2, 1) - r(1, 2)) / s, (r(0, 2) - r(2, 0)) / s, (r(1, 0) - r(0, 1)) / s]
+        }else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2){
+            let s = (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.0;
+            [(r(2, 1) - r(1, 2)) / s, 0.25 * s, (r(0, 1) + r(1, 0)) / s, (r(0, 2) + r(2, 0)) / s]
+        }else if r(1, 1) > r(2, 2){
+            let s = (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.0;
+            [(r(0, 2) - r(2, 0)) / s, (r(0, 1) + r(1, 0)) / s, 0.25 * s, (r(1, 2) + r(2, 1)) / s]
+        }else{
+            let s = (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.0;
+            [(r(1, 0) - r(0, 1)) / s, (r(0, 2) + r(2, 0)) / s, (r(1, 2) + r(2, 1)) / s, 0.25 * s]
+        };
+
+        Decomposition{
+            translation,
+            rotation: [rotation_x.to_degrees(), rotation_y.to_degrees(), rotation_z.to_degrees()],
+            quaternion,
+            scale,
+            shear,
+        }
+    }
+
     ///Returns: The correct 4x4 matrix that can be used
     ///to generate the coefiecients for a bezier curve
     pub fn make_bezier() -> Matrix{
@@ -131,6 +285,43 @@ impl Matrix{
         return matrix;
     }
     
+    ///Returns: The correct 4x4 matrix that can be used
+    ///to generate the coefiecients for one piece of a catmull-rom spline,
+    ///which goes from p1 to p2
+    pub fn make_catmull_rom() -> Matrix{
+        let mut matrix = Matrix::new(4,4);
+        matrix.matrix_array[0][0] = -0.5;
+        matrix.matrix_array[0][1] = 1.5;
+        matrix.matrix_array[0][2] = -1.5;
+        matrix.matrix_array[0][3] = 0.5;
+        matrix.matrix_array[1][0] = 1.0;
+        matrix.matrix_array[1][1] = -2.5;
+        matrix.matrix_array[1][2] = 2.0;
+        matrix.matrix_array[1][3] = -0.5;
+        matrix.matrix_array[2][0] = -0.5;
+        matrix.matrix_array[2][2] = 0.5;
+        matrix.matrix_array[3][1] = 1.0;
+        return matrix;
+    }
+    ///Returns: The correct 4x4 matrix that can be used
+    ///to generate the coefiecients for one piece of a uniform cubic b-spline
+    pub fn make_bspline() -> Matrix{
+        let mut matrix = Matrix::new(4,4);
+        matrix.matrix_array[0][0] = -1.0 / 6.0;
+        matrix.matrix_array[0][1] = 3.0 / 6.0;
+        matrix.matrix_array[0][2] = -3.0 / 6.0;
+        matrix.matrix_array[0][3] = 1.0 / 6.0;
+        matrix.matrix_array[1][0] = 3.0 / 6.0;
+        matrix.matrix_array[1][1] = -6.0 / 6.0;
+        matrix.matrix_array[1][2] = 3.0 / 6.0;
+        matrix.matrix_array[2][0] = -3.0 / 6.0;
+        matrix.matrix_array[2][2] = 3.0 / 6.0;
+        matrix.matrix_array[3][0] = 1.0 / 6.0;
+        matrix.matrix_array[3][1] = 4.0 / 6.0;
+        matrix.matrix_array[3][2] = 1.0 / 6.0;
+        return matrix;
+    }
+    
     /// Inputs:   double p1
     /// 
     /// double p2
@@ -161,10 +352,24 @@ impl Matrix{
             CurveType::Hermite=>{
                 curve_matrix = Matrix::make_hermite();
             }
+            CurveType::CatmullRom=>{
+                curve_matrix = Matrix::make_catmull_rom();
+            }
+            CurveType::BSpline=>{
+                curve_matrix = Matrix::make_bspline();
+            }
+            CurveType::Nurbs{..}=>{
+                panic!("NURBS curves are rational, so they can't be written as at^3 + bt^2 + ct + d. Use add_spline instead.");
+            }
         }
         matrix.multiply_matrixes(&curve_matrix);
         return matrix;
     }
+
+    /// Returns: at^3 + bt^2 + ct + d, using a, b, c and d from a matrix made by generate_curve_coefs
+    pub fn curve_value(&self, t: f32) -> f32{
+        self.matrix_array[0][0] * t.powi(3) + self.matrix_array[1][0] * t.powi(2) + self.matrix_array[2][0] * t + self.matrix_array[3][0]
+    }
 }
 
 impl fmt::Display for Matrix{
@@ -178,4 +383,80 @@ impl fmt::Display for Matrix{
         }
         write!(f, "{}", result)
     }
-}
\ No newline at end of file
+}
+impl fmt::Display for Decomposition{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        writeln!(f, "translation: {} {} {}", self.translation[0], self.translation[1], self.translation[2])?;
+        writeln!(f, "rotation (degrees about x, y, z): {} {} {}", self.rotation[0], self.rotation[1], self.rotation[2])?;
+        writeln!(f, "quaternion (w x y z): {} {} {} {}", self.quaternion[0], self.quaternion[1], self.quaternion[2], self.quaternion[3])?;
+        writeln!(f, "scale: {} {} {}", self.scale[0], self.scale[1], self.scale[2])?;
+        write!(f, "shear (xy xz yz): {} {} {}", self.shear[0], self.shear[1], self.shear[2])
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn close(a: &[f32], b: &[f32]){
+        for (x, y) in a.iter().zip(b){
+            assert!((x - y).abs() < 1e-3, "{:?} is not {:?}", a, b);
+        }
+    }
+
+    /// translate * rotate z * rotate y * rotate x * scale, the same order as a script
+    fn compose(t: [f32; 3], r: [f32; 3], s: [f32; 3]) -> Matrix{
+        let mut matrix = Matrix::make_scale(s[0], s[1], s[2]);
+        matrix.multiply_matrixes(&Matrix::make_rot_x(r[0]));
+        matrix.multiply_matrixes(&Matrix::make_rot_y(r[1]));
+        matrix.multiply_matrixes(&Matrix::make_rot_z(r[2]));
+        matrix.multiply_matrixes(&Matrix::make_translate(t[0], t[1], t[2]));
+        matrix
+    }
+
+    fn same_matrix(a: &Matrix, b: &Matrix){
+        for (row_a, row_b) in a.matrix_array.iter().zip(&b.matrix_array){
+            close(row_a, row_b);
+        }
+    }
+
+    #[test]
+    fn decompose_gives_back_what_made_it(){
+        let d = compose([1.0, -2.0, 3.5], [10.0, 20.0, 30.0], [2.0, 3.0, 4.0]).decompose();
+        close(&d.translation, &[1.0, -2.0, 3.5]);
+        close(&d.rotation, &[10.0, 20.0, 30.0]);
+        close(&d.scale, &[2.0, 3.0, 4.0]);
+        close(&d.shear, &[0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn gimbal_lock_still_makes_the_same_matrix(){
+        let matrix = compose([0.0; 3], [30.0, 90.0, 40.0], [1.0, 2.0, 3.0]);
+        let d = matrix.decompose();
+        close(&d.rotation[1..2], &[90.0]);
+        assert_eq!(d.rotation[2], 0.0);
+        same_matrix(&compose(d.translation, d.rotation, d.scale), &matrix);
+    }
+
+    #[test]
+    fn mirror_flips_one_axis(){
+        let d = Matrix::make_scale(-1.0, 1.0, 1.0).decompose();
+        close(&d.scale, &[-1.0, 1.0, 1.0]);
+        close(&d.rotation, &[0.0, 0.0, 0.0]);
+        let d = Matrix::make_scale(2.0, 1.0, -3.0).decompose();
+        close(&d.scale, &[2.0, 1.0, -3.0]);
+        close(&d.rotation, &[0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn zero_scale_is_not_nan(){
+        for s in [[0.0, 1.0, 1.0], [1.0, 0.0, 2.0], [0.0, 0.0, 5.0], [0.0, 0.0, 0.0]]{
+            let matrix = compose([4.0, 5.0, 6.0], [0.0, 0.0, 25.0], s);
+            let d = matrix.decompose();
+            let all = [&d.rotation[..], &d.quaternion[..], &d.scale[..], &d.shear[..]].concat();
+            assert!(all.iter().all(|n| n.is_finite()), "{}", d);
+            close(&d.scale, &s);
+            same_matrix(&compose(d.translation, d.rotation, d.scale), &matrix);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use crate::color::Color;
+use crate::gmath::{degenerate, subtract};
+use crate::matrix::Matrix;
+use std::collections::HashMap;
+
+/// How close two corners have to be to get welded into one point when nothing else is asked for.
+/// Small enough to never join points that are meant to be apart, but big enough to join up the
+/// seams of shapes like the sphere, where rounding leaves the two sides slightly apart
+pub const WELD_EPSILON: f32 = 0.001;
+
+/// A triangle mesh where each point is stored once and triangles refer to their corners by index,
+/// unlike the polygon matrix where each triangle has its own copy of its 3 corners.
+///
+/// normals, uvs and colors are optional extra values for each point, in the same order as positions
+#[derive(Default)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    /// the corners of each triangle, going counterclockwise when seen from the front
+    pub indices: Vec<[usize; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub uvs: Option<Vec<[f32; 2]>>,
+    pub colors: Option<Vec<Color>>,
+}
+
+impl Mesh {
+    /// Makes a mesh out of the triangles of a polygon matrix, welding together corners
+    /// that are within epsilon of each other
+    pub fn from_polygons(polygons: &Matrix, epsilon: f32) -> Mesh {
+        let mut mesh = Mesh::default();
+        if polygons.matrix_array.is_empty() {
+            return mesh;
+        }
+        for i in 0..polygons.matrix_array[0].len() {
+            mesh.positions.push([
+                polygons.matrix_array[0][i],
+                polygons.matrix_array[1][i],
+                polygons.matrix_array[2][i],
+            ]);
+        }
+        mesh.indices = (0..mesh.positions.len() / 3)
+            .map(|t| [t * 3, t * 3 + 1, t * 3 + 2])
+            .collect();
+        mesh.weld(epsilon);
+        mesh
+    }
+
+    /// Adds every triangle of the mesh to a new polygon matrix
+    pub fn to_polygons(&self) -> Matrix {
+        let mut polygons = Matrix::new(0, 0);
+        for &[a, b, c] in &self.indices {
+            let [ax, ay, az] = self.positions[a];
+            let [bx, by, bz] = self.positions[b];
+            let [cx, cy, cz] = self.positions[c];
+            polygons.add_polygon(ax, ay, az, bx, by, bz, cx, cy, cz);
+        }
+        polygons
+    }
+
+    /// Joins points that are within epsilon of each other into one, keeping the first one's
+    /// position and other values, then removes the triangles this leaves without any area
+    /// and the points that are no longer used
+    pub fn weld(&mut self, epsilon: f32) {
+        // points are sorted into cubes epsilon wide, so only the cubes around a point need checking
+        let cell = |point: &[f32; 3]| {
+            if epsilon > 0.0 {
+                [
+                    (point[0] / epsilon).floor() as i64,
+                    (point[1] / epsilon).floor() as i64,
+                    (point[2] / epsilon).floor() as i64,
+                ]
+            } else {
+                // adding 0 turns -0 into 0 so they count as the same place
+                [
+                    (point[0] + 0.0).to_bits() as i64,
+                    (point[1] + 0.0).to_bits() as i64,
+                    (point[2] + 0.0).to_bits() as i64,
+                ]
+            }
+        };
+        let reach = if epsilon > 0.0 { 1 } else { 0 };
+        let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        // the point each point gets joined into, which is itself for the first one in a place
+        let mut joined: Vec<usize> = vec![];
+        for (i, point) in self.positions.iter().enumerate() {
+            let [x, y, z] = cell(point);
+            let mut found = None;
+            'search: for dx in -reach..reach + 1 {
+                for dy in -reach..reach + 1 {
+                    for dz in -reach..reach + 1 {
+                        for &k in cells.get(&[x + dx, y + dy, z + dz]).into_iter().flatten() {
+                            let offset = subtract(point, &self.positions[k]);
+                            if offset.iter().all(|d| d.abs() <= epsilon) {
+                                found = Some(k);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            joined.push(found.unwrap_or_else(|| {
+                cells.entry([x, y, z]).or_default().push(i);
+                i
+            }));
+        }
+        for triangle in self.indices.iter_mut() {
+            for index in triangle.iter_mut() {
+                *index = joined[*index];
+            }
+        }
+        self.remove_degenerate();
+    }
+
+    /// Removes triangles that have no area, and then any points no triangle uses
+    pub fn remove_degenerate(&mut self) {
+        let positions = &self.positions;
+        self.indices.retain(|&[a, b, c]| {
+            a != b && b != c && c != a && !degenerate(&positions[a], &positions[b], &positions[c])
+        });
+
+        let mut remap = vec![None; self.positions.len()];
+        let mut kept = vec![];
+        for triangle in self.indices.iter_mut() {
+            for index in triangle.iter_mut() {
+                let old = *index;
+                *index = *remap[old].get_or_insert_with(|| {
+                    kept.push(old);
+                    kept.len() - 1
+                });
+            }
+        }
+        self.positions = kept.iter().map(|&i| self.positions[i]).collect();
+        if let Some(normals) = &self.normals {
+            self.normals = Some(kept.iter().map(|&i| normals[i]).collect());
+        }
+        if let Some(uvs) = &self.uvs {
+            self.uvs = Some(kept.iter().map(|&i| uvs[i]).collect());
+        }
+        if let Some(colors) = &self.colors {
+            self.colors = Some(kept.iter().map(|&i| colors[i]).collect());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_corners_are_shared() {
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_box(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+        let mesh = Mesh::from_polygons(&polygons, WELD_EPSILON);
+        assert_eq!(mesh.positions.len(), 8);
+        assert_eq!(mesh.indices.len(), 12);
+        assert_eq!(mesh.to_polygons().matrix_array[0].len(), 36);
+    }
+
+    #[test]
+    fn weld_joins_only_close_points() {
+        let mut mesh = Mesh {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [1.0005, 0.0, 0.0],
+                [1.0, 1.0, 0.0],
+                [0.0, 1.0002, 0.0],
+            ],
+            indices: vec![[0, 1, 2], [3, 4, 5]],
+            ..Mesh::default()
+        };
+        mesh.weld(0.0);
+        assert_eq!(mesh.positions.len(), 6);
+        mesh.weld(WELD_EPSILON);
+        assert_eq!(mesh.positions.len(), 4);
+        // the second triangle now uses the first one's corners
+        assert_eq!(mesh.indices[1], [1, 3, 2]);
+    }
+
+    #[test]
+    fn degenerate_triangles_and_their_points_are_removed() {
+        let mut mesh = Mesh {
+            positions: vec![
+                [0.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [2.0, 0.0, 0.0],
+                [3.0, 0.0, 0.0],
+            ],
+            // the second triangle is 3 points on a line
+            indices: vec![[0, 1, 2], [1, 3, 4]],
+            normals: Some(vec![[0.0, 0.0, 1.0]; 5]),
+            ..Mesh::default()
+        };
+        mesh.remove_degenerate();
+        assert_eq!(mesh.indices, vec![[0, 1, 2]]);
+        assert_eq!(mesh.positions.len(), 3);
+        assert_eq!(mesh.normals.unwrap().len(), 3);
+    }
+}
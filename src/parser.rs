@@ -1,7 +1,13 @@
 use crate::color::Color;
-use crate::image::Image;
+use crate::csg::Operation;
+use crate::expr::{self, Expr};
+use crate::image::{Filter, Image};
 use crate::matrix::CurveType;
 use crate::matrix::Matrix;
+use crate::mesh::{Mesh, WELD_EPSILON};
+use crate::shapes::{star_points, FillRule, Overlay, Shape};
+use crate::stroke::{Cap, Join};
+use std::collections::HashMap;
 use std::f32;
 use std::fs::File;
 use std::io::{self, prelude::*, BufReader};
@@ -22,6 +28,8 @@ use std::process::Command;
 
 /// ident: set the transform matrix to the identity matrix -
 
+/// Numeric arguments may be integers, decimals or expressions without spaces, such as 12.5, -3, 2*pi or 100*sin(pi/4)
+
 /// scale: create a scale matrix,
 /// then multiply the transform matrix by the scale matrix -
 /// takes 3 arguments (sx, sy, sz)
@@ -34,6 +42,10 @@ use std::process::Command;
 /// then multiply the transform matrix by the rotation matrix -
 /// takes 2 arguments (axis, theta) axis should be x y or z
 
+/// print: print the transform matrix, followed by the translation, rotation, scale and shear that make it up
+
+/// show: same as print - takes 1 argument (what to show), which must be transform
+
 /// apply: apply the current transformation matrix to the edge matrix
 
 /// display: clear the screen, then
@@ -45,6 +57,19 @@ use std::process::Command;
 /// save the screen to a file -
 /// takes 1 argument (file name)
 
+/// near: set the near clipping plane, anything with a larger z is not drawn -
+/// takes 1 argument (z), use inf to turn near clipping off
+
+/// antialias: turn anti-aliased lines on or off - takes 1 argument (on or off)
+
+/// supersample: render display and save at N times the size and filter back down, 1 turns it off -
+/// takes 1 or 2 arguments (N, filter) where filter is box (the default) or tent
+
+/// linewidth: sets how many pixels wide lines are drawn - takes 1 to 3 arguments (width, cap, join).
+/// cap is how the ends of lines look: butt (the default), round or square.
+/// join is how corners where lines meet look: miter (the default), roundjoin or bevel.
+/// Lines wider than 1 pixel are never anti-aliased, use supersample to smooth them
+
 /// quit: end parsing
 ///
 /// circle: add a circle to the edge matrix -
@@ -52,11 +77,13 @@ use std::process::Command;
 ///
 /// hermite: add a hermite curve to the edge matrix -
 ///          takes 8 arguments (x0, y0, x1, y1, rx0, ry0, rx1, ry1)
+///          or 12 for a 3D curve (x0, y0, z0, x1, y1, z1, rx0, ry0, rz0, rx1, ry1, rz1)
 ///
 /// bezier: add a bezier curve to the edge matrix -
 ///         takes 8 arguments (x0, y0, x1, y1, x2, y2, x3, y3)
+///         or 12 for a 3D curve (x0, y0, z0, x1, y1, z1, x2, y2, z2, x3, y3, z3)
 ///
-/// clear: clears the edge matrix of all points
+/// clear: clears the edge matrix of all points, along with the polygon matrix and the flat shapes
 ///
 /// box: adds a rectangular prism (box) to the edge matrix - takes 6 parameters (x, y, z, width, height, depth)
 ///
@@ -68,6 +95,154 @@ use std::process::Command;
 ///
 /// radius2 is the full radius of the torus (the translation factor). You can think of this as the distance from the center of the torus to the center of any circular slice of the torus.
 ///
+/// cylinder: adds a capped cylinder to the polygon matrix - takes 5 parameters (x, y, z, radius, height)
+///
+/// (x, y, z) is the center of the bottom, and the cylinder goes up height along the y axis
+///
+/// cone: adds a cone to the polygon matrix - takes 5 parameters (x, y, z, radius, height)
+///
+/// (x, y, z) is the center of the base, and the tip is height above it along the y axis
+///
+/// icosphere: adds a sphere made from a subdivided icosahedron to the polygon matrix -
+/// takes 4 or 5 parameters (x, y, z, radius, level), where level is how many times each face is split into 4 (default 2)
+///
+/// lathe: spins a profile around an axis through the origin and adds the surface to the polygon matrix -
+/// takes an axis (x, y or z), a profile type, then the profile
+///
+/// poly profiles are a list of points (r0, h0, r1, h1, ...), where r is the distance from the axis and
+/// h is how far along it. bezier and hermite profiles take the same 8 arguments as the bezier and hermite commands
+///
+/// The profile should go in the positive direction along the axis so the outside faces out
+///
+/// The step of a poly profile is how many pieces to split the way around the axis into. bezier and hermite profiles
+/// can take 2 steps, how many lines to split the profile curve into and then how many pieces to split the way around into.
+/// With only one step, it is used for both
+///
+/// extrude: pushes a closed 2D outline back along z to make a solid, adding it to the polygon matrix -
+/// takes an outline type, then (z, depth), then the outline. The front is at z and the back at z - depth
+///
+/// poly outlines are a list of points (x0, y0, x1, y1, ...), circle outlines are (cx, cy, r),
+/// and bezier and hermite outlines take the same 8 arguments as the bezier and hermite commands,
+/// with a straight line joining the end back to the start
+///
+/// bezier_patch: adds a bicubic bezier surface patch to the polygon matrix -
+/// takes 48 arguments, the (x, y, z) of 16 control points as 4 rows of 4
+///
+/// hermite_patch: adds a bicubic hermite surface patch to the polygon matrix -
+/// takes 48 arguments like bezier_patch, where the rows and columns both go (p0, p1, r0, r1)
+///
+/// teapot: adds the Utah teapot to the polygon matrix - takes 4 arguments (x, y, z, size)
+///
+/// (x, y, z) is the center of the teapot, which stands up along y and is 3.15 * size tall
+///
+/// catmullrom: adds a catmull-rom spline to the edge matrix - takes any number of points (x0, y0, x1, y1, ...),
+/// at least 4. The curve goes through every point except the first and last
+///
+/// bspline: adds a uniform cubic b-spline to the edge matrix - takes points like catmullrom
+///
+/// nurbs: adds a NURBS curve to the edge matrix - takes (degree, n), then n points with weights
+/// (x0, y0, w0, x1, y1, w1, ...), then optionally n + degree + 1 knots, and optionally a step.
+/// Without knots the curve starts at the first point and ends at the last.
+/// The step is how many lines to use between each pair of knots
+///
+/// tube: adds a round tube around a path to the polygon matrix, with both ends capped -
+/// takes (radius, path type), then the path
+///
+/// poly paths are a list of points (x0, y0, z0, x1, y1, z1, ...), and bezier and hermite paths
+/// take the same 8 or 12 arguments as the bezier and hermite commands
+///
+/// The step of a poly path is how many pieces to split the way around the tube into. bezier and hermite paths
+/// can take 2 steps, how many lines to split the path curve into and then how many pieces to split the way around into.
+/// With only one step, it is used for both
+///
+/// sweep: like tube, but with any closed cross section - takes 2 lines of arguments,
+/// the cross section (x0, y0, x1, y1, ...) on the first and the path type and path on the second.
+/// x goes out from the path and y goes out sideways from both x and the path
+///
+/// parametric: adds the surface made by 3 expressions of u and v to the polygon matrix -
+/// takes 2 lines of arguments, the expressions for x, y and z on the first and
+/// (u0, u1, ustep, v0, v1, vstep) on the second, where u goes from u0 to u1 in ustep pieces
+/// and v goes from v0 to v1 in vstep pieces
+///
+/// The surface faces the viewer where going along u and then along v turns counterclockwise
+///
+/// terrain: adds a landscape made from a grayscale image to the polygon matrix, where brighter pixels are higher -
+/// takes 7 arguments (file, x, y, z, spacing_x, spacing_z, height). file is a PPM or PGM image,
+/// or screen to use what is on the screen right now
+///
+/// The top left pixel is at (x, y, z), columns go along x and rows go along z spacing_x and spacing_z apart,
+/// and the brightest pixels are height above y
+///
+/// isosurface: adds the surface where an expression of x, y and z is 0 to the polygon matrix -
+/// takes 2 lines of arguments, the expression on the first and the box to look in (x0, y0, z0, x1, y1, z1) on the second.
+/// The expression is negative on the inside, so sqrt(x^2+y^2+z^2)-50 is a sphere of radius 50
+///
+/// metaball: adds blobs that melt into each other where they get close to the polygon matrix -
+/// takes any number of balls (x0, y0, z0, r0, x1, y1, z1, r1, ...). On its own each ball is a sphere of radius r
+///
+/// group: starts a group of shapes, which go into the group instead of the polygon matrix
+/// until endgroup - takes 1 argument (name). Groups can be started inside other groups
+///
+/// endgroup: ends the last group that was started
+///
+/// union: adds everything that is in either of two groups to the polygon matrix - takes 2 arguments (a, b), the group names
+///
+/// intersection: adds only what is in both of two groups to the polygon matrix - takes 2 arguments (a, b)
+///
+/// difference: adds what is in group a but not in group b to the polygon matrix - takes 2 arguments (a, b)
+///
+/// The shapes in a group should be closed solids like box, sphere and torus, and a group can be used more than once
+///
+/// subdivide: smooths everything in the polygon matrix with Loop subdivision, which splits each triangle into 4 -
+/// takes 1 argument (N), how many times to do it. Each time makes 4 times as many triangles.
+/// It works best on shapes whose triangles share their edges, like box and icosphere
+///
+/// weld: snaps together the corners in the polygon matrix that are within epsilon of each other,
+/// and removes the triangles that leaves with no area - takes 1 argument (epsilon).
+/// Something small like 0.001 joins up points that only missed each other by rounding
+///
+/// simplify: cuts down how many triangles are in the polygon matrix while keeping its shape as much as possible -
+/// takes 1 argument (N), how many triangles to leave, or 2 arguments (error, e) to keep going
+/// until the surface would move more than about e away from where it was
+///
+/// color: sets the color everything is drawn in - takes 3 arguments (r, g, b), each from 0 to 255
+///
+/// rect, ellipse, poly2d, arc and star draw flat shapes straight onto the screen in the current color,
+/// on top of everything else, each time it is displayed or saved. They all start with how to draw the shape:
+/// stroke for just the outline, or fill to fill it in. poly2d can also use nonzero (the same as fill)
+/// or evenodd, which leaves holes where the outline crosses over itself an even number of times
+///
+/// rect: takes 5 arguments (style, x, y, width, height), where (x, y) is the bottom left corner
+///
+/// ellipse: takes 5 arguments (style, cx, cy, rx, ry)
+///
+/// poly2d: takes the style then any number of corners (x0, y0, x1, y1, ...)
+///
+/// arc: takes 6 arguments (style, cx, cy, r, start, end), where start and end are in degrees
+/// counterclockwise from the right. A filled arc is a pie slice
+///
+/// star: takes 6 arguments (style, cx, cy, outer, inner, points), where outer and inner are
+/// how far the tips and the dips between them are from the center
+///
+/// text: writes text on the screen in the same way, in a 5x7 pixel font -
+/// takes (x, y), the text in double quotes, and optionally a scale, such as 20 20 "frame 1" 2.
+/// (x, y) is the bottom left corner of the first letter, each pixel of the font is drawn scale pixels wide,
+/// and \n starts a new line under the last one
+///
+/// text3d: adds text in the simplex Hershey font to the edge matrix, so it can be moved around with apply like any other lines -
+/// takes (x, y, z, height), the text in double quotes, and optionally a depth, such as 0 0 0 40 "hello" 10.
+/// (x, y, z) is the left end of the line the first letter sits on and capital letters are height tall.
+/// With a depth, each line of the letters is made into a bar going from z back to z - depth in the polygon matrix instead
+///
+/// circle, hermite, bezier, catmullrom, bspline, sphere, torus, cylinder, cone, lathe, extrude (except poly), tube, sweep (except poly), isosurface, metaball, bezier_patch, hermite_patch and teapot can all take one more argument at the end,
+/// the step, which is how many pieces to split the shape into
+///
+/// detail: sets how finely shapes are split up when they don't give a step -
+/// takes 1 argument, either a number to multiply the usual step by (1 is normal, 0.5 is half as many pieces)
+/// or adaptive, which can be followed by how many pixels long each piece should be on the screen (default 5).
+/// adaptive only guesses how big a shape is on the screen from its size and the biggest scale of the current
+/// transform, so a transform added after the shape is drawn doesn't change its step
+///
 /// See the file script for an example of the file format
 pub fn parse_file(
     fname: &str,
@@ -75,12 +250,16 @@ pub fn parse_file(
     polygons: &mut Matrix,
     transform: &mut Matrix,
     screen: &mut Image,
-    color: Color,
+    mut color: Color,
 ) -> io::Result<()> {
     let file = File::open(&fname)?;
     let reader = BufReader::new(file);
     let mut doc_lines = vec![String::new(); 0];
     let mut i = 0;
+    let mut detail = Detail::Scale(1.0);
+    let mut groups: HashMap<String, Matrix> = HashMap::new();
+    // the name of each group that has been started, and what was in the polygon matrix before it
+    let mut open_groups: Vec<(String, Matrix)> = vec![];
 
     for line in reader.lines() {
         doc_lines.push(line?);
@@ -90,10 +269,7 @@ pub fn parse_file(
         match &*doc_lines[i] {
             "line" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
                 points.add_edge(
                     params[0], params[1], params[2], params[3], params[4], params[5],
                 );
@@ -103,42 +279,31 @@ pub fn parse_file(
             }
             "scale" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
 
                 transform.multiply_matrixes(&Matrix::make_scale(params[0], params[1], params[2]));
             }
             "translate" | "move" => {
                 i += 1;
-                let mut params = vec![0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
 
                 transform
                     .multiply_matrixes(&Matrix::make_translate(params[0], params[1], params[2]));
             }
             "rotate" => {
                 i += 1;
-                let mut params = vec![""; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input);
-                }
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let theta = parse_number(params[1], i + 1);
 
                 match params[0] {
                     "x" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_x(params[1].parse().unwrap()));
+                        transform.multiply_matrixes(&Matrix::make_rot_x(theta));
                     }
                     "y" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_y(params[1].parse().unwrap()));
+                        transform.multiply_matrixes(&Matrix::make_rot_y(theta));
                     }
                     "z" => {
-                        transform
-                            .multiply_matrixes(&Matrix::make_rot_z(params[1].parse().unwrap()));
+                        transform.multiply_matrixes(&Matrix::make_rot_z(theta));
                     }
                     _ => {
                         panic!(
@@ -148,6 +313,24 @@ pub fn parse_file(
                     }
                 }
             }
+            "print" => {
+                print_transform(transform);
+            }
+            "show" => {
+                i += 1;
+                match doc_lines[i].trim() {
+                    "transform" => {
+                        print_transform(transform);
+                    }
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for show: please use transform.",
+                            doc_lines[i],
+                            i + 1
+                        );
+                    }
+                }
+            }
             "apply" => {
                 if points.matrix_array.len() > 0 {
                     points.multiply_matrixes(&transform);
@@ -157,23 +340,11 @@ pub fn parse_file(
                 }
             }
             "display" => {
-                screen.clear();
-                if points.matrix_array.len() > 0 {
-                    screen.draw_lines(&points, color);
-                }
-                if polygons.matrix_array.len() > 0 {
-                    screen.draw_polygons(&polygons, color);
-                }
+                screen.render(points, polygons, color);
                 screen.display();
             }
             "save" => {
-                screen.clear();
-                if points.matrix_array.len() > 0 {
-                    screen.draw_lines(&points, color);
-                }
-                if polygons.matrix_array.len() > 0 {
-                    screen.draw_polygons(&polygons, color);
-                }
+                screen.render(points, polygons, color);
                 i += 1;
                 screen.create_file(&*doc_lines[i]);
                 Command::new("magick")
@@ -183,69 +354,99 @@ pub fn parse_file(
                     .spawn()
                     .expect("failed to convert image to desired format");
             }
+            "near" => {
+                i += 1;
+                screen.near = parse_number(doc_lines[i].trim(), i + 1);
+            }
+            "antialias" => {
+                i += 1;
+                screen.antialias = match doc_lines[i].trim() {
+                    "on" => true,
+                    "off" => false,
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for antialias: please use on or off.",
+                            doc_lines[i],
+                            i + 1
+                        );
+                    }
+                };
+            }
+            "supersample" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                screen.supersample = parse_number(params[0], i + 1).max(1.0) as usize;
+                if params.len() > 1 {
+                    screen.filter = match params[1] {
+                        "box" => Filter::Box,
+                        "tent" => Filter::Tent,
+                        _ => {
+                            panic!(
+                                "Invalid input {} at line {} for supersample: please use box or tent.",
+                                params[1],
+                                i + 1
+                            );
+                        }
+                    };
+                }
+            }
+            "detail" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                detail = if params[0] == "adaptive" {
+                    let pixels = if params.len() > 1 {
+                        parse_number(params[1], i + 1)
+                    } else {
+                        5.0
+                    };
+                    Detail::Adaptive(pixels)
+                } else {
+                    Detail::Scale(parse_number(params[0], i + 1))
+                };
+            }
             "quit" => {
                 break;
             }
             "circle" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let step = detail.step(
+                    &params,
+                    4,
+                    100,
+                    2.0 * f32::consts::PI * params[3],
+                    transform,
+                );
 
-                points.add_circle(params[0], params[1], params[2], params[3], 100);
+                points.add_circle(params[0], params[1], params[2], params[3], step);
             }
             "hermite" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let (p, count) = curve_args(&params, 1, false, "hermite", i + 1);
+                let size = curve_length(&p, &CurveType::Hermite);
+                let step = detail.step(&params, count, 100, size, transform);
 
-                points.add_curve(
-                    params[0],
-                    params[1],
-                    params[2],
-                    params[3],
-                    params[4],
-                    params[5],
-                    params[6],
-                    params[7],
-                    100,
-                    &CurveType::Hermite,
-                );
+                points.add_curve(&p, step, &CurveType::Hermite);
             }
             "bezier" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let (p, count) = curve_args(&params, 1, false, "bezier", i + 1);
+                let size = curve_length(&p, &CurveType::Bezier);
+                let step = detail.step(&params, count, 100, size, transform);
 
-                points.add_curve(
-                    params[0],
-                    params[1],
-                    params[2],
-                    params[3],
-                    params[4],
-                    params[5],
-                    params[6],
-                    params[7],
-                    100,
-                    &CurveType::Bezier,
-                );
+                points.add_curve(&p, step, &CurveType::Bezier);
             }
             _ if doc_lines[i].starts_with('#') => {}
             "clear" => {
                 *points = Matrix::new(0, 0);
                 *polygons = Matrix::new(0, 0);
+                screen.overlays.clear();
             }
             "box" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
 
                 polygons.add_box(
                     params[0], params[1], params[2], params[3], params[4], params[5],
@@ -253,21 +454,659 @@ pub fn parse_file(
             }
             "sphere" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
-                }
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let step =
+                    detail.step(&params, 4, 20, 2.0 * f32::consts::PI * params[3], transform);
 
-                polygons.add_sphere(params[0], params[1], params[2], params[3], 20);
+                polygons.add_sphere(params[0], params[1], params[2], params[3], step);
             }
             "torus" => {
                 i += 1;
-                let mut params = vec![0.0; 0];
-                for input in doc_lines[i].split(' ') {
-                    params.push(input.parse().unwrap());
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let size = 2.0 * f32::consts::PI * (params[3] + params[4]);
+                let step = detail.step(&params, 5, 20, size, transform);
+
+                polygons.add_torus(params[0], params[1], params[2], params[3], params[4], step);
+            }
+            "cylinder" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let step =
+                    detail.step(&params, 5, 20, 2.0 * f32::consts::PI * params[3], transform);
+
+                polygons.add_cylinder(params[0], params[1], params[2], params[3], params[4], step);
+            }
+            "cone" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let step =
+                    detail.step(&params, 5, 20, 2.0 * f32::consts::PI * params[3], transform);
+
+                polygons.add_cone(params[0], params[1], params[2], params[3], params[4], step);
+            }
+            "icosphere" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let level = if params.len() > 4 {
+                    params[4] as i32
+                } else {
+                    2
+                };
+
+                polygons.add_icosphere(params[0], params[1], params[2], params[3], level);
+            }
+            "lathe" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let axis = match params[0] {
+                    "x" => 'x',
+                    "y" => 'y',
+                    "z" => 'z',
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for lathe: please use x, y, or z.",
+                            params[0],
+                            i + 1
+                        );
+                    }
+                };
+                let numbers: Vec<f32> = params[2..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let profile = match params[1] {
+                    "poly" => {
+                        let mut profile = Matrix::new(0, 0);
+                        for point in numbers.chunks_exact(2) {
+                            profile.add_point(point[0], point[1], 0.0);
+                        }
+                        profile
+                    }
+                    "bezier" | "hermite" => {
+                        let curve_type = if params[1] == "bezier" {
+                            CurveType::Bezier
+                        } else {
+                            CurveType::Hermite
+                        };
+                        let (p, _) = curve_args(&numbers, 2, true, "lathe", i + 1);
+                        let size = curve_length(&p, &curve_type);
+                        let step = detail.step(&numbers, 8, 20, size, transform);
+                        Matrix::generate_curve(&p, step, &curve_type)
+                    }
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for lathe: please use poly, bezier or hermite.",
+                            params[1],
+                            i + 1
+                        );
+                    }
+                };
+                // a poly profile has an odd number of numbers when it ends with a step.
+                // A curve profile can have a second step for going around, otherwise its one step does both
+                let count = if params[1] == "poly" {
+                    numbers.len() / 2 * 2
+                } else if numbers.len() > 9 {
+                    9
+                } else {
+                    8
+                };
+                let widest = profile.matrix_array[0]
+                    .iter()
+                    .fold(0.0_f32, |widest, r| widest.max(r.abs()));
+                let step = detail.step(
+                    &numbers,
+                    count,
+                    20,
+                    2.0 * f32::consts::PI * widest,
+                    transform,
+                );
+
+                polygons.add_lathe(&profile, axis, step);
+            }
+            "extrude" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let numbers: Vec<f32> = params[1..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let (z, depth, shape) = (numbers[0], numbers[1], &numbers[2..]);
+                let outline = match params[0] {
+                    "poly" => {
+                        let mut outline = Matrix::new(0, 0);
+                        for point in shape.chunks_exact(2) {
+                            outline.add_point(point[0], point[1], 0.0);
+                        }
+                        outline
+                    }
+                    "circle" => {
+                        let step =
+                            detail.step(shape, 3, 20, 2.0 * f32::consts::PI * shape[2], transform);
+                        let mut outline = Matrix::new(0, 0);
+                        for t in 0..step {
+                            let theta = 2.0 * f32::consts::PI * t as f32 / step as f32;
+                            outline.add_point(
+                                shape[0] + shape[2] * theta.cos(),
+                                shape[1] + shape[2] * theta.sin(),
+                                0.0,
+                            );
+                        }
+                        outline
+                    }
+                    "bezier" | "hermite" => {
+                        let curve_type = if params[0] == "bezier" {
+                            CurveType::Bezier
+                        } else {
+                            CurveType::Hermite
+                        };
+                        let (p, _) = curve_args(shape, 1, true, "extrude", i + 1);
+                        let size = curve_length(&p, &curve_type);
+                        let step = detail.step(shape, 8, 20, size, transform);
+                        Matrix::generate_curve(&p, step, &curve_type)
+                    }
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for extrude: please use poly, circle, bezier or hermite.",
+                            params[0],
+                            i + 1
+                        );
+                    }
+                };
+
+                polygons.add_extrusion(&outline, z, depth);
+            }
+            "bezier_patch" | "hermite_patch" => {
+                let curve_type = if doc_lines[i] == "bezier_patch" {
+                    CurveType::Bezier
+                } else {
+                    CurveType::Hermite
+                };
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let mut controls = Matrix::new(0, 0);
+                for point in params[..48].chunks_exact(3) {
+                    controls.add_point(point[0], point[1], point[2]);
+                }
+                // how far it is across the patch, corner to corner
+                let size = ((params[45] - params[0]).powi(2)
+                    + (params[46] - params[1]).powi(2)
+                    + (params[47] - params[2]).powi(2))
+                .sqrt();
+                let step = detail.step(&params, 48, 8, size, transform);
+
+                polygons.add_patch(&controls, step, &curve_type);
+            }
+            "teapot" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                // each patch goes a quarter of the way around the body, which has a radius of 2
+                let step = detail.step(&params, 4, 8, f32::consts::PI * params[3], transform);
+
+                polygons.add_teapot(params[0], params[1], params[2], params[3], step);
+            }
+            "catmullrom" | "bspline" => {
+                let curve_type = if doc_lines[i] == "catmullrom" {
+                    CurveType::CatmullRom
+                } else {
+                    CurveType::BSpline
+                };
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let count = params.len() / 2 * 2;
+                if count < 8 {
+                    panic!(
+                        "{} at line {} needs at least 4 points.",
+                        doc_lines[i - 1],
+                        i + 1
+                    );
                 }
+                let mut controls = Matrix::new(0, 0);
+                for point in params[..count].chunks_exact(2) {
+                    controls.add_point(point[0], point[1], 0.0);
+                }
+                // each piece is only about as long as the gap between two points
+                let size = control_length(&params, 2) / 3.0;
+                let step = detail.step(&params, count, 20, size, transform);
 
-                polygons.add_torus(params[0], params[1], params[2], params[3], params[4], 20);
+                points.add_spline(&controls, step, &curve_type);
+            }
+            "nurbs" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let degree = params[0] as usize;
+                let count = params[1] as usize;
+                if count <= degree {
+                    panic!(
+                        "Invalid input {} at line {} for nurbs: a degree {} curve needs at least {} points, not {}.",
+                        doc_lines[i],
+                        i + 1,
+                        degree,
+                        degree + 1,
+                        count
+                    );
+                }
+                if params.len() < 2 + count * 3 {
+                    panic!(
+                        "Invalid input {} at line {} for nurbs: {} points need {} numbers (x, y, w for each), not {}.",
+                        doc_lines[i],
+                        i + 1,
+                        count,
+                        count * 3,
+                        params.len() - 2
+                    );
+                }
+                let mut controls = Matrix::new(0, 0);
+                let mut weights = vec![];
+                for point in params[2..2 + count * 3].chunks_exact(3) {
+                    controls.add_point(point[0], point[1], 0.0);
+                    weights.push(point[2]);
+                }
+                let rest = &params[2 + count * 3..];
+                let knot_count = count + degree + 1;
+                if rest.len() > 1 && rest.len() != knot_count && rest.len() != knot_count + 1 {
+                    panic!(
+                        "Invalid input {} at line {} for nurbs: after the points, please use a step, {} knots, or {} knots and a step, not {} numbers.",
+                        doc_lines[i],
+                        i + 1,
+                        knot_count,
+                        knot_count,
+                        rest.len()
+                    );
+                }
+                let knots = if rest.len() > 1 {
+                    rest[..knot_count].to_vec()
+                } else {
+                    // clamped so the curve starts and ends on the first and last points
+                    (0..knot_count)
+                        .map(|k| {
+                            (k.saturating_sub(degree) as f32 / (count - degree) as f32).min(1.0)
+                        })
+                        .collect()
+                };
+                // the step is the last number, unless there is nothing but knots after the points
+                let step_index = if rest.len() == 1 || rest.len() == knot_count + 1 {
+                    params.len() - 1
+                } else {
+                    params.len()
+                };
+                let mut size = 0.0;
+                for p in 1..count {
+                    size += ((controls.matrix_array[0][p] - controls.matrix_array[0][p - 1])
+                        .powi(2)
+                        + (controls.matrix_array[1][p] - controls.matrix_array[1][p - 1]).powi(2))
+                    .sqrt();
+                }
+                let spans = count.saturating_sub(degree).max(1) as f32;
+                let step = detail.step(&params, step_index, 20, size / spans, transform);
+
+                points.add_spline(
+                    &controls,
+                    step,
+                    &CurveType::Nurbs {
+                        degree,
+                        weights,
+                        knots,
+                    },
+                );
+            }
+            "tube" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let numbers: Vec<f32> = params[2..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let radius = parse_number(params[0], i + 1);
+                let (path, mut count) =
+                    sweep_path(params[1], &numbers, 2, &detail, transform, i + 1);
+                // a curve path can have a second step for going around, otherwise its one step does both
+                if params[1] != "poly" && numbers.len() > count + 1 {
+                    count += 1;
+                }
+                let step = detail.step(
+                    &numbers,
+                    count,
+                    12,
+                    2.0 * f32::consts::PI * radius,
+                    transform,
+                );
+                let mut profile = Matrix::new(0, 0);
+                for t in 0..step {
+                    let theta = 2.0 * f32::consts::PI * t as f32 / step as f32;
+                    profile.add_point(radius * theta.cos(), radius * theta.sin(), 0.0);
+                }
+
+                polygons.add_sweep(&profile, &path);
+            }
+            "sweep" => {
+                i += 1;
+                let shape = parse_numbers(&doc_lines[i], i + 1);
+                let mut profile = Matrix::new(0, 0);
+                for point in shape.chunks_exact(2) {
+                    profile.add_point(point[0], point[1], 0.0);
+                }
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let numbers: Vec<f32> = params[1..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let (path, _) = sweep_path(params[0], &numbers, 1, &detail, transform, i + 1);
+
+                polygons.add_sweep(&profile, &path);
+            }
+            "parametric" => {
+                i += 1;
+                let exprs: Vec<Expr> = doc_lines[i]
+                    .split_whitespace()
+                    .map(|text| match Expr::parse(text) {
+                        Ok(expr) => expr,
+                        Err(message) => {
+                            panic!(
+                                "Invalid input {} at line {} for parametric: {}.",
+                                text,
+                                i + 1,
+                                message
+                            );
+                        }
+                    })
+                    .collect();
+                if exprs.len() != 3 {
+                    panic!(
+                        "Invalid input at line {} for parametric: please give 3 expressions for x, y and z.",
+                        i + 1
+                    );
+                }
+                let expr_line = i + 1;
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let surface = |u: f32, v: f32| {
+                    let mut point = [0.0; 3];
+                    for (value, expr) in point.iter_mut().zip(&exprs) {
+                        *value = match expr.eval(&[("u", u), ("v", v)]) {
+                            Ok(value) => value,
+                            Err(message) => {
+                                panic!(
+                                    "Invalid input at line {} for parametric: {}.",
+                                    expr_line, message
+                                );
+                            }
+                        };
+                    }
+                    point
+                };
+
+                polygons.add_parametric(
+                    &surface,
+                    (params[0], params[1], (params[2] as i32).max(1)),
+                    (params[3], params[4], (params[5] as i32).max(1)),
+                );
+            }
+            "terrain" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let numbers: Vec<f32> = params[1..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let file;
+                let heights = if params[0] == "screen" {
+                    &*screen
+                } else {
+                    file = Image::read_file(params[0]);
+                    &file
+                };
+
+                polygons.add_terrain(
+                    heights,
+                    [numbers[0], numbers[1], numbers[2]],
+                    [numbers[3], numbers[4]],
+                    numbers[5],
+                );
+            }
+            "isosurface" => {
+                i += 1;
+                let field = match Expr::parse(&doc_lines[i]) {
+                    Ok(expr) => expr,
+                    Err(message) => {
+                        panic!(
+                            "Invalid input {} at line {} for isosurface: {}.",
+                            doc_lines[i],
+                            i + 1,
+                            message
+                        );
+                    }
+                };
+                let expr_line = i + 1;
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let size = (params[3] - params[0])
+                    .abs()
+                    .max((params[4] - params[1]).abs())
+                    .max((params[5] - params[2]).abs());
+                let res = detail.step(&params, 6, 20, size, transform);
+                let value =
+                    |x: f32, y: f32, z: f32| match field.eval(&[("x", x), ("y", y), ("z", z)]) {
+                        Ok(value) => value,
+                        Err(message) => {
+                            panic!(
+                                "Invalid input at line {} for isosurface: {}.",
+                                expr_line, message
+                            );
+                        }
+                    };
+
+                polygons.add_isosurface(
+                    &value,
+                    [params[0], params[1], params[2]],
+                    [params[3], params[4], params[5]],
+                    res as usize,
+                );
+            }
+            "metaball" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                let count = params.len() / 4 * 4;
+                let balls: Vec<&[f32]> = params[..count].chunks_exact(4).collect();
+                // each ball adds (r / distance)^2, and the surface is where they add up to 1.
+                // That can't be further than sqrt(n) * r from any of the n balls
+                let reach = (balls.len() as f32).sqrt();
+                let mut min = [f32::INFINITY; 3];
+                let mut max = [f32::NEG_INFINITY; 3];
+                for ball in &balls {
+                    for axis in 0..3 {
+                        min[axis] = min[axis].min(ball[axis] - ball[3].abs() * reach);
+                        max[axis] = max[axis].max(ball[axis] + ball[3].abs() * reach);
+                    }
+                }
+                let size = (max[0] - min[0]).max(max[1] - min[1]).max(max[2] - min[2]);
+                let res = detail.step(&params, count, 20, size, transform);
+                let value = |x: f32, y: f32, z: f32| {
+                    let mut total = 0.0;
+                    for ball in &balls {
+                        let distance =
+                            (x - ball[0]).powi(2) + (y - ball[1]).powi(2) + (z - ball[2]).powi(2);
+                        // a corner right on a center would be infinitely far inside
+                        total += ball[3] * ball[3] / distance.max(1e-6);
+                    }
+                    1.0 - total
+                };
+
+                polygons.add_isosurface(&value, min, max, res as usize);
+            }
+            "group" => {
+                i += 1;
+                let saved = std::mem::replace(polygons, Matrix::new(0, 0));
+                open_groups.push((doc_lines[i].trim().to_string(), saved));
+            }
+            "endgroup" => {
+                let (name, saved) = match open_groups.pop() {
+                    Some(group) => group,
+                    None => {
+                        panic!("endgroup at line {} has no group to end.", i + 1);
+                    }
+                };
+                groups.insert(name, std::mem::replace(polygons, saved));
+            }
+            "union" | "intersection" | "difference" => {
+                let operation = match &*doc_lines[i] {
+                    "union" => Operation::Union,
+                    "intersection" => Operation::Intersection,
+                    _ => Operation::Difference,
+                };
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let shapes: Vec<&Matrix> = params
+                    .iter()
+                    .map(|name| match groups.get(*name) {
+                        Some(shape) => shape,
+                        None => {
+                            panic!(
+                                "Invalid input {} at line {} for {}: there is no group with that name.",
+                                name,
+                                i + 1,
+                                doc_lines[i - 1]
+                            );
+                        }
+                    })
+                    .collect();
+                if shapes.len() != 2 {
+                    panic!(
+                        "Invalid input at line {} for {}: please give 2 group names.",
+                        i + 1,
+                        doc_lines[i - 1]
+                    );
+                }
+
+                polygons.add_csg(shapes[0], shapes[1], &operation);
+            }
+            "subdivide" => {
+                i += 1;
+                let times = parse_number(doc_lines[i].trim(), i + 1) as i32;
+                for _ in 0..times {
+                    polygons.subdivide();
+                }
+            }
+            "weld" => {
+                i += 1;
+                let epsilon = parse_number(doc_lines[i].trim(), i + 1);
+                *polygons = Mesh::from_polygons(polygons, epsilon).to_polygons();
+            }
+            "simplify" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let (target, max_error) = match params[..] {
+                    ["error", e] => (0, parse_number(e, i + 1)),
+                    [n] => (parse_number(n, i + 1) as usize, f32::INFINITY),
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for simplify: please use a triangle count or error and a distance.",
+                            doc_lines[i],
+                            i + 1
+                        );
+                    }
+                };
+                let mut mesh = Mesh::from_polygons(polygons, WELD_EPSILON);
+                mesh.simplify(target, max_error);
+                *polygons = mesh.to_polygons();
+            }
+            "color" => {
+                i += 1;
+                let params = parse_numbers(&doc_lines[i], i + 1);
+                color = Color::new_color(params[0] as i32, params[1] as i32, params[2] as i32);
+            }
+            "rect" | "ellipse" | "poly2d" | "arc" | "star" => {
+                let command = &doc_lines[i];
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                let fill = match params[0] {
+                    "stroke" => None,
+                    "fill" | "nonzero" => Some(FillRule::NonZero),
+                    "evenodd" => Some(FillRule::EvenOdd),
+                    _ => {
+                        panic!(
+                            "Invalid input {} at line {} for {}: please use stroke, fill, nonzero or evenodd.",
+                            params[0],
+                            i + 1,
+                            command
+                        );
+                    }
+                };
+                let n: Vec<f32> = params[1..]
+                    .iter()
+                    .map(|arg| parse_number(arg, i + 1))
+                    .collect();
+                let shape = match &command[..] {
+                    "rect" => Shape::Rect {
+                        x: n[0],
+                        y: n[1],
+                        width: n[2],
+                        height: n[3],
+                    },
+                    "ellipse" => Shape::Ellipse {
+                        cx: n[0],
+                        cy: n[1],
+                        rx: n[2],
+                        ry: n[3],
+                    },
+                    "poly2d" => Shape::Polygon(n.chunks_exact(2).map(|p| [p[0], p[1]]).collect()),
+                    "arc" => Shape::Arc {
+                        cx: n[0],
+                        cy: n[1],
+                        r: n[2],
+                        start: n[3],
+                        end: n[4],
+                    },
+                    _ => Shape::Polygon(star_points(n[0], n[1], n[2], n[3], n[4] as usize)),
+                };
+
+                screen.overlays.push(Overlay { shape, fill, color });
+            }
+            "text" => {
+                i += 1;
+                let (position, text, rest) = split_quoted(&doc_lines[i], i + 1, "text");
+                let shape = Shape::Text {
+                    x: position[0],
+                    y: position[1],
+                    text,
+                    scale: rest.first().copied().unwrap_or(1.0),
+                };
+                screen.overlays.push(Overlay {
+                    shape,
+                    fill: None,
+                    color,
+                });
+            }
+            "text3d" => {
+                i += 1;
+                let (params, text, rest) = split_quoted(&doc_lines[i], i + 1, "text3d");
+                match rest.first() {
+                    Some(&depth) => polygons
+                        .add_text_solid(params[0], params[1], params[2], params[3], depth, &text),
+                    None => points.add_text(params[0], params[1], params[2], params[3], &text),
+                }
+            }
+            "linewidth" => {
+                i += 1;
+                let params: Vec<&str> = doc_lines[i].split_whitespace().collect();
+                screen.line_width = parse_number(params[0], i + 1);
+                for arg in &params[1..] {
+                    match *arg {
+                        "butt" => screen.cap = Cap::Butt,
+                        "round" => screen.cap = Cap::Round,
+                        "square" => screen.cap = Cap::Square,
+                        "miter" => screen.join = Join::Miter,
+                        "roundjoin" => screen.join = Join::Round,
+                        "bevel" => screen.join = Join::Bevel,
+                        _ => {
+                            panic!(
+                                "Invalid input {} at line {} for linewidth: please use butt, round, square, miter, roundjoin or bevel.",
+                                arg,
+                                i + 1
+                            );
+                        }
+                    }
+                }
             }
             _ => {
                 panic!("Invalid command {} at line {}.", doc_lines[i], i + 1);
@@ -275,5 +1114,542 @@ pub fn parse_file(
         }
         i += 1;
     }
+    // the shapes in a group only get kept when it ends
+    if let Some((name, _)) = open_groups.last() {
+        panic!("group {} has no endgroup before the end of the file.", name);
+    }
     Ok(())
 }
+
+/// How finely curved shapes are split up when they don't give their own step
+enum Detail {
+    /// multiply each shape's usual step by this
+    Scale(f32),
+    /// pick the step so each piece is about this many pixels long on the screen,
+    /// going by the shape's size times the biggest scale in the transform
+    Adaptive(f32),
+}
+
+impl Detail {
+    /// Picks the step for a shape, using the optional argument after the first count params
+    /// if it was given.
+    ///
+    /// size is roughly how far around the shape goes (such as 2 pi r for a circle), and transform
+    /// is used to guess how much bigger or smaller the shape will be on the screen. It is only a
+    /// guess, since the transform is the one the shape is added with: the biggest of its scales is
+    /// used for every direction, and the screen position and any later transforms are ignored.
+    fn step(
+        &self,
+        params: &[f32],
+        count: usize,
+        default: i32,
+        size: f32,
+        transform: &Matrix,
+    ) -> i32 {
+        if params.len() > count {
+            return (params[count] as i32).max(1);
+        }
+        match self {
+            Detail::Scale(factor) => ((default as f32 * factor).round() as i32).max(3),
+            Detail::Adaptive(pixels) => {
+                let scale = transform.decompose().scale;
+                let mut factor = scale[0].abs().max(scale[1].abs()).max(scale[2].abs());
+                // the transform hasn't been set up yet
+                if !factor.is_normal() {
+                    factor = 1.0;
+                }
+                ((size.abs() * factor / pixels).ceil() as i32).clamp(6, 400)
+            }
+        }
+    }
+}
+
+/// The length of the path through the first 4 control points of a curve, which the curve can't be longer than.
+///
+/// dims is how many numbers there are for each point
+fn control_length(params: &[f32], dims: usize) -> f32 {
+    let mut length = 0.0;
+    for p in 0..3 {
+        let mut squared = 0.0;
+        for axis in 0..dims {
+            squared += (params[(p + 1) * dims + axis] - params[p * dims + axis]).powi(2);
+        }
+        length += f32::sqrt(squared);
+    }
+    length
+}
+
+/// About how long a bezier or hermite curve is, measured along 16 straight pieces of it.
+///
+/// This only looks at points on the curve, since a hermite curve's tangents aren't distances
+fn curve_length(p: &[f32; 12], curve_type: &CurveType) -> f32 {
+    let curve = Matrix::generate_curve(p, 16, curve_type).matrix_array;
+    let mut length = 0.0;
+    for n in 1..curve[0].len() {
+        let squared: f32 = curve[..3]
+            .iter()
+            .map(|row| (row[n] - row[n - 1]).powi(2))
+            .sum();
+        length += squared.sqrt();
+    }
+    length
+}
+
+/// The 4 control points of a hermite or bezier curve as (x0, y0, z0, x1, ...), and how many
+/// of params they took up. 8 numbers are 2D points with z = 0 and 12 are 3D points, unless flat
+/// is true and only 2D points are allowed. Up to steps more numbers can come after the points.
+///
+/// Anything else panics, saying it was wrong for command at line_num
+fn curve_args(
+    params: &[f32],
+    steps: usize,
+    flat: bool,
+    command: &str,
+    line_num: usize,
+) -> ([f32; 12], usize) {
+    let count = params.len();
+    let mut p = [0.0; 12];
+    if !flat && (12..=12 + steps).contains(&count) {
+        p.copy_from_slice(&params[..12]);
+        (p, 12)
+    } else if (8..=8 + steps).contains(&count) {
+        for point in 0..4 {
+            p[point * 3] = params[point * 2];
+            p[point * 3 + 1] = params[point * 2 + 1];
+        }
+        (p, 8)
+    } else {
+        let input: Vec<String> = params.iter().map(|n| n.to_string()).collect();
+        let points = if flat {
+            "8 numbers for the points"
+        } else {
+            "8 numbers for 2D points or 12 for 3D points"
+        };
+        let after = if steps == 1 {
+            "a step".to_string()
+        } else {
+            format!("up to {} steps", steps)
+        };
+        panic!(
+            "Invalid input {} at line {} for {}: please use {} and optionally {} after them, not {} numbers.",
+            input.join(" "),
+            line_num,
+            command,
+            points,
+            after,
+            count
+        );
+    }
+}
+
+/// The path for tube and sweep from its type and numbers, and how many of the numbers it took up.
+///
+/// poly paths are a list of points (x0, y0, z0, x1, ...), and bezier and hermite paths take
+/// the same arguments as the bezier and hermite commands, with up to steps steps after them
+fn sweep_path(
+    kind: &str,
+    numbers: &[f32],
+    steps: usize,
+    detail: &Detail,
+    transform: &Matrix,
+    line_num: usize,
+) -> (Matrix, usize) {
+    match kind {
+        "poly" => {
+            let mut path = Matrix::new(0, 0);
+            for point in numbers.chunks_exact(3) {
+                path.add_point(point[0], point[1], point[2]);
+            }
+            (path, numbers.len() / 3 * 3)
+        }
+        "bezier" | "hermite" => {
+            let curve_type = if kind == "bezier" {
+                CurveType::Bezier
+            } else {
+                CurveType::Hermite
+            };
+            let (p, count) = curve_args(numbers, steps, false, "the path", line_num);
+            let size = curve_length(&p, &curve_type);
+            let step = detail.step(numbers, count, 20, size, transform);
+            let path = Matrix::generate_curve(&p, step, &curve_type);
+            (path, count)
+        }
+        _ => {
+            panic!(
+                "Invalid input {} at line {} for the path: please use poly, bezier or hermite.",
+                kind, line_num
+            );
+        }
+    }
+}
+
+fn print_transform(transform: &Matrix) {
+    transform.print_matrix();
+    println!("{}\n", transform.decompose());
+}
+
+/// Splits a line like 20 20 "some text" 2 into the numbers before the quotes, the text
+/// in them (with each \n turned into a new line) and the numbers after them
+fn split_quoted(line: &str, line_num: usize, command: &str) -> (Vec<f32>, String, Vec<f32>) {
+    match (line.find('"'), line.rfind('"')) {
+        (Some(start), Some(end)) if start < end => (
+            parse_numbers(&line[..start], line_num),
+            line[start + 1..end].replace("\\n", "\n"),
+            parse_numbers(&line[end + 1..], line_num),
+        ),
+        _ => {
+            panic!(
+                "Invalid input {} at line {} for {}: please put the text in quotes, like 20 20 \"hello\".",
+                line, line_num, command
+            );
+        }
+    }
+}
+
+/// Parses every whitespace separated argument on line as a number or expression
+fn parse_numbers(line: &str, line_num: usize) -> Vec<f32> {
+    line.split_whitespace()
+        .map(|arg| parse_number(arg, line_num))
+        .collect()
+}
+
+fn parse_number(arg: &str, line_num: usize) -> f32 {
+    match expr::evaluate(arg) {
+        Ok(value) => value,
+        Err(error) => panic!("Invalid argument {} at line {}: {}.", arg, line_num, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csg::tests::{assert_closed, volume};
+    use std::{env, fs, process};
+
+    /// Runs script through parse_file, giving back the edge, polygon and transform matrices
+    fn run(name: &str, script: &str) -> (Matrix, Matrix, Matrix) {
+        let (points, polygons, transform, _) = run_on_screen(name, script);
+        (points, polygons, transform)
+    }
+
+    /// Like run, but also gives back the screen
+    fn run_on_screen(name: &str, script: &str) -> (Matrix, Matrix, Matrix, Image) {
+        let path = env::temp_dir().join(format!("parser_test_{}_{}", process::id(), name));
+        fs::write(&path, script).unwrap();
+        let mut points = Matrix::new(0, 0);
+        let mut polygons = Matrix::new(0, 0);
+        let mut transform = Matrix::new(4, 4);
+        let mut screen = Image::new(500, 500);
+        let result = parse_file(
+            path.to_str().unwrap(),
+            &mut points,
+            &mut polygons,
+            &mut transform,
+            &mut screen,
+            Color::new(),
+        );
+        fs::remove_file(&path).unwrap();
+        result.unwrap();
+        (points, polygons, transform, screen)
+    }
+
+    fn assert_matrix_eq(a: &Matrix, b: &Matrix) {
+        for (row_a, row_b) in a.matrix_array.iter().zip(&b.matrix_array) {
+            for (x, y) in row_a.iter().zip(row_b) {
+                assert!((x - y).abs() < 1e-5, "{} is not {}", a, b);
+            }
+        }
+    }
+
+    /// Every point of m as (x, y, z)
+    fn points_of(m: &Matrix) -> Vec<[f32; 3]> {
+        (0..m.matrix_array[0].len())
+            .map(|i| {
+                [
+                    m.matrix_array[0][i],
+                    m.matrix_array[1][i],
+                    m.matrix_array[2][i],
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbers_can_be_integers_fractions_or_expressions() {
+        assert_eq!(parse_number("12", 1), 12.0);
+        assert_eq!(parse_number("12.5", 1), 12.5);
+        assert_eq!(parse_number("-3", 1), -3.0);
+        assert_eq!(parse_number("1e2", 1), 100.0);
+        assert_eq!(parse_number("2*pi", 1), 2.0 * f32::consts::PI);
+        assert_eq!(parse_numbers("12 12.5 3/4", 1), vec![12.0, 12.5, 0.75]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid argument 1..2 at line 3")]
+    fn bad_numbers_say_where_they_are() {
+        parse_number("1..2", 3);
+    }
+
+    #[test]
+    fn near_can_be_turned_off() {
+        let (_, _, _, screen) = run_on_screen("near", "near\n-10\n");
+        assert_eq!(screen.near, -10.0);
+        let (_, _, _, screen) = run_on_screen("near_inf", "near\n-10\nnear\ninf\n");
+        assert_eq!(screen.near, f32::INFINITY);
+    }
+
+    #[test]
+    fn move_takes_fractions() {
+        let (_, _, transform) = run("move", "ident\nmove\n12.5 0 0\n");
+        assert_matrix_eq(&transform, &Matrix::make_translate(12.5, 0.0, 0.0));
+
+        let (_, _, transform) = run("move_expr", "ident\nmove\n10/4 -1.5 2*2\n");
+        assert_matrix_eq(&transform, &Matrix::make_translate(2.5, -1.5, 4.0));
+    }
+
+    #[test]
+    fn scale_takes_fractions() {
+        let (_, _, transform) = run("scale", "ident\nscale\n2 0.5 3/2\n");
+        assert_matrix_eq(&transform, &Matrix::make_scale(2.0, 0.5, 1.5));
+    }
+
+    #[test]
+    fn rotate_takes_fractions() {
+        let (_, _, transform) = run("rotate", "ident\nrotate\nz 22.5\n");
+        assert_matrix_eq(&transform, &Matrix::make_rot_z(22.5));
+
+        let (_, _, transform) = run("rotate_expr", "ident\nrotate\ny 90/4\n");
+        assert_matrix_eq(&transform, &Matrix::make_rot_y(22.5));
+    }
+
+    #[test]
+    fn line_takes_fractions() {
+        let (points, _, _) = run("line", "line\n0 1.5 -2 10*2 sqrt(16) 1e1\n");
+        assert_eq!(
+            points_of(&points),
+            vec![[0.0, 1.5, -2.0], [20.0, 4.0, 10.0]]
+        );
+    }
+
+    #[test]
+    fn circle_takes_fractions() {
+        let (points, _, _) = run("circle", "circle\n1.5 0 0 2.5 8\n");
+        let points = points_of(&points);
+        assert!(!points.is_empty());
+        for [x, y, _] in points {
+            assert!(((x - 1.5).hypot(y) - 2.5).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn hermite_takes_fractions() {
+        for (name, args) in [
+            ("hermite", "0 0 10.5 0 0 0 0 0 4"),
+            ("hermite_expr", "0 0 21/2 0 0 0 0 0 2*2"),
+        ] {
+            let (points, _, _) = run(name, &format!("hermite\n{}\n", args));
+            let points = points_of(&points);
+            assert_eq!(points.len(), 8);
+            assert_eq!(points[0], [0.0, 0.0, 0.0]);
+            assert_eq!(points[7], [10.5, 0.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn bezier_takes_fractions() {
+        for (name, args) in [
+            ("bezier", "0.5 0 1.5 3 7.5 3 9 0 4"),
+            ("bezier_expr", "1/2 0 1.5 3 15/2 3 3^2 0 2+2"),
+        ] {
+            let (points, _, _) = run(name, &format!("bezier\n{}\n", args));
+            let points = points_of(&points);
+            assert_eq!(points.len(), 8);
+            assert_eq!(points[0], [0.5, 0.0, 0.0]);
+            assert_eq!(points[7], [9.0, 0.0, 0.0]);
+            // halfway along, 3/4 of the way up to the middle control points
+            assert_eq!(points[3][1], 2.25);
+        }
+    }
+
+    #[test]
+    fn torus_takes_fractions() {
+        let (_, polygons, _) = run("torus", "torus\n0 0 0 1.5 10/2 12\n");
+        let points = points_of(&polygons);
+        assert!(!points.is_empty());
+        for [x, y, z] in points {
+            assert!(((x.hypot(z) - 5.0).hypot(y) - 1.5).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn box_takes_fractions() {
+        let (_, polygons, _) = run("box", "box\n0.5 0 0 1.5 2*1 1/2\n");
+        let points = points_of(&polygons);
+        assert_eq!(points.len(), 36);
+        for axis in 0..3 {
+            let low = points.iter().fold(f32::INFINITY, |low, p| low.min(p[axis]));
+            let high = points
+                .iter()
+                .fold(f32::NEG_INFINITY, |high, p| high.max(p[axis]));
+            assert_eq!(high - low, [1.5, 2.0, 0.5][axis]);
+        }
+    }
+
+    #[test]
+    fn nurbs_takes_a_step_knots_or_both() {
+        let points = "2 4 0 0 1 100 0 1 100 100 1 0 100 1";
+        let lines: Vec<usize> = ["", " 5", " 0 0 0 0.5 1 1 1", " 0 0 0 0.5 1 1 1 5"]
+            .iter()
+            .enumerate()
+            .map(|(k, rest)| {
+                let script = format!("nurbs\n{}{}\n", points, rest);
+                run(&format!("nurbs_{}", k), &script).0.matrix_array[0].len()
+            })
+            .collect();
+        // the knots given are the same as the ones made up without them
+        assert_eq!(lines[0], lines[2]);
+        assert_eq!(lines[1], lines[3]);
+        assert!(lines[1] < lines[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "for nurbs: a degree 3 curve needs at least 4 points, not 2")]
+    fn nurbs_needs_more_points_than_its_degree() {
+        run("nurbs_degree", "nurbs\n3 2 0 0 1 100 100 1\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "please use a step, 7 knots, or 7 knots and a step, not 3 numbers")]
+    fn nurbs_rejects_some_knots() {
+        run(
+            "nurbs_partial",
+            "nurbs\n2 4 0 0 1 100 0 1 100 100 1 0 100 1 0 0 1\n",
+        );
+    }
+
+    #[test]
+    fn detail_uses_a_given_step_first() {
+        let identity = Matrix::make_translate(0.0, 0.0, 0.0);
+        for detail in [Detail::Scale(2.0), Detail::Adaptive(5.0)] {
+            assert_eq!(detail.step(&[1.0, 2.0, 7.0], 2, 20, 100.0, &identity), 7);
+            assert_eq!(detail.step(&[1.0, 2.0, 0.0], 2, 20, 100.0, &identity), 1);
+        }
+    }
+
+    #[test]
+    fn detail_scales_the_usual_step() {
+        let identity = Matrix::make_translate(0.0, 0.0, 0.0);
+        assert_eq!(Detail::Scale(1.0).step(&[], 0, 20, 1.0, &identity), 20);
+        assert_eq!(Detail::Scale(0.5).step(&[], 0, 20, 1.0, &identity), 10);
+        assert_eq!(Detail::Scale(0.01).step(&[], 0, 20, 1.0, &identity), 3);
+    }
+
+    #[test]
+    fn adaptive_detail_follows_the_size_on_the_screen() {
+        let detail = Detail::Adaptive(5.0);
+        let identity = Matrix::make_translate(0.0, 0.0, 0.0);
+        assert_eq!(detail.step(&[], 0, 20, 100.0, &identity), 20);
+        let bigger = Matrix::make_scale(1.0, 3.0, 2.0);
+        assert_eq!(detail.step(&[], 0, 20, 100.0, &bigger), 60);
+        // before ident everything is squashed to a point, so the size is used as it is
+        assert_eq!(detail.step(&[], 0, 20, 100.0, &Matrix::new(4, 4)), 20);
+        assert_eq!(detail.step(&[], 0, 20, 1.0, &identity), 6);
+        assert_eq!(detail.step(&[], 0, 20, 1e6, &identity), 400);
+    }
+
+    #[test]
+    fn hermite_size_does_not_count_tangents() {
+        // a straight line 100 long, with long tangents along it
+        let p = [
+            0.0, 0.0, 0.0, 100.0, 0.0, 0.0, 300.0, 0.0, 0.0, 300.0, 0.0, 0.0,
+        ];
+        assert!((curve_length(&p, &CurveType::Hermite) - 100.0).abs() < 0.5);
+        let p = [
+            0.0, 0.0, 0.0, 0.0, 100.0, 0.0, 100.0, 100.0, 0.0, 100.0, 0.0, 0.0,
+        ];
+        let length = curve_length(&p, &CurveType::Bezier);
+        assert!(length > 100.0 && length < 300.0);
+    }
+
+    #[test]
+    fn metaball_on_a_grid_corner_is_not_nan() {
+        // with 2 cells the center of the ball is a corner of the grid
+        let (_, polygons, _) = run("metaball", "metaball\n0 0 0 50 2\n");
+        let points = points_of(&polygons);
+        assert!(!points.is_empty());
+        assert!(points.iter().flatten().all(|n| n.is_finite()));
+    }
+
+    #[test]
+    fn bezier_takes_3d_points() {
+        let (points, _, _) = run("bezier_3d", "bezier\n0 0 0 10 0 50 20 10 50 30 10 0 10\n");
+        let points = points_of(&points);
+        assert_eq!(points.len(), 20);
+        assert!(points.iter().any(|p| p[2] > 10.0));
+        assert!(points.iter().all(|p| p[2] >= 0.0 && p[2] <= 50.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "and optionally a step after them, not 10 numbers")]
+    fn bezier_rejects_points_with_missing_numbers() {
+        run("bezier_10", "bezier\n0 0 0 10 0 50 20 10 50 30\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "at line 2 for hermite: please use 8 numbers")]
+    fn hermite_needs_4_points() {
+        run("hermite_6", "hermite\n0 0 10 10 1 1\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "for lathe: please use 8 numbers for the points")]
+    fn lathe_profile_is_flat() {
+        run("lathe_3d", "lathe\ny bezier 0 0 0 10 0 0 10 10 0 0 20 0\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "group inner has no endgroup before the end of the file")]
+    fn groups_have_to_end() {
+        run("groups", "group\nouter\ngroup\ninner\nbox\n0 0 0 1 1 1\n");
+    }
+
+    #[test]
+    fn sphere_takes_expressions() {
+        let (_, polygons, _) = run("sphere", "sphere\n0 0 0 pi 10\n");
+        let points = points_of(&polygons);
+        assert!(!points.is_empty());
+        for [x, y, z] in points {
+            assert!(((x * x + y * y + z * z).sqrt() - f32::consts::PI).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn tubes_are_closed_and_face_out() {
+        let (_, straight, _) = run("tube_poly", "tube\n2 poly 0 0 0 0 10 0 0 20 0 16\n");
+        assert_closed(&straight);
+        let around = 8.0 * 4.0 * (f32::consts::PI / 8.0).sin();
+        assert!((volume(&straight) - around * 20.0).abs() < 0.01);
+
+        let (_, curved, _) = run("tube_bezier", "tube\n2 bezier 0 0 30 0 30 30 30 30 20 16\n");
+        assert_closed(&curved);
+        assert!(volume(&curved) > 0.0);
+    }
+
+    #[test]
+    fn quoted_text_keeps_its_spaces() {
+        let (before, text, after) = split_quoted("20 2*5 \"a  b \" c\" 3", 1, "text");
+        assert_eq!(before, vec![20.0, 10.0]);
+        // everything from the first quote to the last
+        assert_eq!(text, "a  b \" c");
+        assert_eq!(after, vec![3.0]);
+        let (_, text, after) = split_quoted("0 0 \"one\\ntwo\"", 1, "text");
+        assert_eq!(text, "one\ntwo");
+        assert!(after.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "for text3d: please put the text in quotes")]
+    fn quoted_text_has_to_end() {
+        split_quoted("0 0 \"hello", 1, "text3d");
+    }
+}
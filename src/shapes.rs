@@ -0,0 +1,449 @@
+use crate::color::Color;
+use crate::image::Image;
+use std::f32;
+
+/// Which parts of a polygon whose outline crosses over itself get filled
+#[derive(Copy, Clone)]
+pub enum FillRule {
+    /// filled where a line going out from the point crosses the outline an odd number of times
+    EvenOdd,
+    /// filled wherever the outline goes around the point at all
+    NonZero,
+}
+
+/// A flat shape drawn straight onto the screen, in pixels
+#[derive(Clone)]
+pub enum Shape {
+    /// (x, y) is the bottom left corner
+    Rect {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    Ellipse {
+        cx: f32,
+        cy: f32,
+        rx: f32,
+        ry: f32,
+    },
+    /// the corners of a closed polygon
+    Polygon(Vec<[f32; 2]>),
+    /// part of a circle from start to end degrees, going counterclockwise from the +x direction.
+    /// A filled arc is a pie slice
+    Arc {
+        cx: f32,
+        cy: f32,
+        r: f32,
+        start: f32,
+        end: f32,
+    },
+    /// text in the built in font, where (x, y) is the bottom left corner of the first letter
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        scale: f32,
+    },
+}
+
+/// A shape that gets drawn on top of everything else each time the screen is rendered
+#[derive(Clone)]
+pub struct Overlay {
+    pub shape: Shape,
+    /// how to fill the shape, or None to only draw its outline
+    pub fill: Option<FillRule>,
+    pub color: Color,
+}
+
+impl Overlay {
+    /// The same overlay on a screen k times as big
+    pub fn scaled(&self, k: f32) -> Overlay {
+        let shape = match &self.shape {
+            Shape::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => Shape::Rect {
+                x: x * k,
+                y: y * k,
+                width: width * k,
+                height: height * k,
+            },
+            Shape::Ellipse { cx, cy, rx, ry } => Shape::Ellipse {
+                cx: cx * k,
+                cy: cy * k,
+                rx: rx * k,
+                ry: ry * k,
+            },
+            Shape::Polygon(points) => {
+                Shape::Polygon(points.iter().map(|p| [p[0] * k, p[1] * k]).collect())
+            }
+            Shape::Arc {
+                cx,
+                cy,
+                r,
+                start,
+                end,
+            } => Shape::Arc {
+                cx: cx * k,
+                cy: cy * k,
+                r: r * k,
+                start: *start,
+                end: *end,
+            },
+            Shape::Text { x, y, text, scale } => Shape::Text {
+                x: x * k,
+                y: y * k,
+                text: text.clone(),
+                scale: scale * k,
+            },
+        };
+        Overlay {
+            shape,
+            fill: self.fill,
+            color: self.color,
+        }
+    }
+}
+
+/// The corners of a star with the given number of points, with the first point straight up
+pub fn star_points(cx: f32, cy: f32, outer: f32, inner: f32, points: usize) -> Vec<[f32; 2]> {
+    (0..points * 2)
+        .map(|i| {
+            let r = if i % 2 == 0 { outer } else { inner };
+            let theta = f32::consts::FRAC_PI_2 + f32::consts::PI * i as f32 / points as f32;
+            [cx + r * theta.cos(), cy + r * theta.sin()]
+        })
+        .collect()
+}
+
+/// Points along an arc, about 2 pixels apart
+fn arc_points(cx: f32, cy: f32, r: f32, start: f32, end: f32) -> Vec<[f32; 2]> {
+    let (start, end) = (start.to_radians(), end.to_radians());
+    let pieces = ((r.abs() * (end - start).abs() / 2.0).ceil() as usize).max(4);
+    (0..pieces + 1)
+        .map(|i| {
+            let theta = start + (end - start) * i as f32 / pieces as f32;
+            [cx + r * theta.cos(), cy + r * theta.sin()]
+        })
+        .collect()
+}
+
+/// Points around an ellipse, about 2 pixels apart
+fn ellipse_points(cx: f32, cy: f32, rx: f32, ry: f32) -> Vec<[f32; 2]> {
+    let pieces = ((f32::consts::PI * (rx.abs() + ry.abs()) / 2.0).ceil() as usize).max(8);
+    (0..pieces)
+        .map(|i| {
+            let theta = 2.0 * f32::consts::PI * i as f32 / pieces as f32;
+            [cx + rx * theta.cos(), cy + ry * theta.sin()]
+        })
+        .collect()
+}
+
+impl Image {
+    /// Draws every overlay, in the order they were added
+    pub fn draw_overlays(&mut self) {
+        let overlays = std::mem::take(&mut self.overlays);
+        for overlay in &overlays {
+            self.draw_overlay(overlay);
+        }
+        self.overlays = overlays;
+    }
+
+    pub fn draw_overlay(&mut self, overlay: &Overlay) {
+        let color = overlay.color;
+        match (&overlay.shape, overlay.fill) {
+            (
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                },
+                fill,
+            ) => {
+                let corners = [
+                    [*x, *y],
+                    [x + width, *y],
+                    [x + width, y + height],
+                    [*x, y + height],
+                ];
+                match fill {
+                    Some(rule) => self.fill_polygon(&corners, rule, color),
+                    None => self.stroke_polyline(&corners, true, color),
+                }
+            }
+            (Shape::Ellipse { cx, cy, rx, ry }, None) if self.line_width > 1.0 => {
+                if *rx == 0.0 || *ry == 0.0 {
+                    // a flat ellipse is just a line across it
+                    self.stroke_polyline(&[[cx - rx, cy - ry], [cx + rx, cy + ry]], false, color);
+                } else {
+                    let points = ellipse_points(*cx, *cy, *rx, *ry);
+                    self.stroke_polyline(&points, true, color);
+                }
+            }
+            (Shape::Ellipse { cx, cy, rx, ry }, fill) => self.draw_ellipse(
+                cx.round() as i32,
+                cy.round() as i32,
+                rx.abs().round() as i32,
+                ry.abs().round() as i32,
+                color,
+                fill.is_some(),
+            ),
+            (Shape::Polygon(points), Some(rule)) => self.fill_polygon(points, rule, color),
+            (Shape::Polygon(points), None) => self.stroke_polyline(points, true, color),
+            (
+                Shape::Arc {
+                    cx,
+                    cy,
+                    r,
+                    start,
+                    end,
+                },
+                fill,
+            ) => {
+                let mut points = arc_points(*cx, *cy, *r, *start, *end);
+                match fill {
+                    Some(rule) => {
+                        points.push([*cx, *cy]);
+                        self.fill_polygon(&points, rule, color);
+                    }
+                    None => self.stroke_polyline(&points, false, color),
+                }
+            }
+            (Shape::Text { x, y, text, scale }, _) => self.draw_text(*x, *y, text, *scale, color),
+        }
+    }
+
+    /// Draws lines between each of points, and back to the start if closed
+    pub fn stroke_polyline(&mut self, points: &[[f32; 2]], closed: bool, color: Color) {
+        let count = if closed {
+            points.len()
+        } else {
+            points.len().saturating_sub(1)
+        };
+        // flat shapes sit on the near plane so it never cuts them off
+        let z = self.near.min(0.0);
+        let mut segments = vec![];
+        for i in 0..count {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            if let Some((x0, y0, _, x1, y1, _)) = self.clip_line(a[0], a[1], z, b[0], b[1], z) {
+                segments.push(([x0, y0], [x1, y1]));
+            }
+        }
+        self.stroke_segments(&segments, color);
+    }
+
+    /// Draws an ellipse centered at (cx, cy) with the midpoint algorithm, filled in if fill is true
+    pub fn draw_ellipse(&mut self, cx: i32, cy: i32, rx: i32, ry: i32, color: Color, fill: bool) {
+        if rx == 0 || ry == 0 {
+            // the slopes below are never both nonzero, so a flat ellipse is drawn as the line it is
+            for x in -rx..rx + 1 {
+                for y in -ry..ry + 1 {
+                    self.plot(cx + x, cy + y, color);
+                }
+            }
+            return;
+        }
+        let (rx2, ry2) = (rx as i64 * rx as i64, ry as i64 * ry as i64);
+        let (mut x, mut y) = (0_i64, ry as i64);
+        // the slopes of the curve so far, which tell us which region we are in
+        let mut px = 0;
+        let mut py = 2 * rx2 * y;
+        let plot_four = |image: &mut Image, x: i64, y: i64| {
+            let (x, y) = (x as i32, y as i32);
+            if fill {
+                for col in cx - x..cx + x + 1 {
+                    image.plot(col, cy + y, color);
+                    if y != 0 {
+                        image.plot(col, cy - y, color);
+                    }
+                }
+            } else {
+                image.plot(cx + x, cy + y, color);
+                image.plot(cx - x, cy + y, color);
+                image.plot(cx + x, cy - y, color);
+                image.plot(cx - x, cy - y, color);
+            }
+        };
+
+        // region 1, where the curve is flatter than 45 degrees and x goes up every step
+        let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+        while px < py {
+            plot_four(self, x, y);
+            x += 1;
+            px += 2 * ry2;
+            if p < 0 {
+                p += ry2 + px;
+            } else {
+                y -= 1;
+                py -= 2 * rx2;
+                p += ry2 + px - py;
+            }
+        }
+        // region 2, where the curve is steeper and y goes down every step
+        let mut p = ry2 * (x * x + x) + ry2 / 4 + rx2 * (y - 1) * (y - 1) - rx2 * ry2;
+        while y >= 0 {
+            // filled rows in region 1 get redrawn here when y didn't change, which is harmless
+            plot_four(self, x, y);
+            y -= 1;
+            py -= 2 * rx2;
+            if p > 0 {
+                p += rx2 - py;
+            } else {
+                x += 1;
+                px += 2 * ry2;
+                p += rx2 - py + px;
+            }
+        }
+    }
+
+    /// Fills the polygon through points one row of pixels at a time, using rule to decide
+    /// what is inside where the outline crosses itself
+    pub fn fill_polygon(&mut self, points: &[[f32; 2]], rule: FillRule, color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let lowest = points.iter().fold(f32::INFINITY, |low, p| low.min(p[1]));
+        let highest = points
+            .iter()
+            .fold(f32::NEG_INFINITY, |high, p| high.max(p[1]));
+        let bottom = (lowest.ceil() as i32).max(0);
+        let top = (highest.floor() as i32).min(self.height as i32 - 1);
+        for row in bottom..top + 1 {
+            let y = row as f32;
+            // where each side crosses this row, and whether it is going up or down
+            let mut crossings: Vec<(f32, i32)> = vec![];
+            for i in 0..points.len() {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                if (a[1] <= y && b[1] > y) || (b[1] <= y && a[1] > y) {
+                    let x = a[0] + (y - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+                    crossings.push((x, if b[1] > a[1] { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match rule {
+                    FillRule::EvenOdd => winding % 2 != 0,
+                    FillRule::NonZero => winding != 0,
+                };
+                if inside {
+                    let start = (pair[0].0.ceil() as i32).max(0);
+                    let end = (pair[1].0.ceil() as i32).min(self.width as i32);
+                    for col in start..end {
+                        self.plot(col, row, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit(image: &Image, x: i32, y: i32) -> bool {
+        image.screen[image.height - 1 - y as usize][x as usize].r != 0
+    }
+
+    fn lit_count(image: &Image) -> usize {
+        image.screen.iter().flatten().filter(|c| c.r != 0).count()
+    }
+
+    fn white() -> Color {
+        Color::new_color(255, 255, 255)
+    }
+
+    /// A 5 pointed star drawn in one go, so its outline crosses itself around the middle
+    fn pentagram() -> Vec<[f32; 2]> {
+        (0..5)
+            .map(|i| {
+                let theta = f32::consts::FRAC_PI_2 + 4.0 * f32::consts::PI * i as f32 / 5.0;
+                [50.0 + 40.0 * theta.cos(), 50.0 + 40.0 * theta.sin()]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn even_odd_leaves_the_middle_of_a_pentagram_empty() {
+        let mut image = Image::new(100, 100);
+        image.fill_polygon(&pentagram(), FillRule::EvenOdd, white());
+        assert!(!lit(&image, 50, 50));
+        // the top point
+        assert!(lit(&image, 50, 85));
+    }
+
+    #[test]
+    fn nonzero_fills_the_middle_of_a_pentagram() {
+        let mut image = Image::new(100, 100);
+        image.fill_polygon(&pentagram(), FillRule::NonZero, white());
+        assert!(lit(&image, 50, 50));
+        assert!(lit(&image, 50, 85));
+        assert!(!lit(&image, 5, 5));
+    }
+
+    #[test]
+    fn ellipse_outline_stays_on_the_ellipse() {
+        let mut image = Image::new(100, 100);
+        image.draw_ellipse(50, 50, 30, 10, white(), false);
+        for (x, y) in [(80, 50), (20, 50), (50, 60), (50, 40)] {
+            assert!(lit(&image, x, y));
+        }
+        assert!(!lit(&image, 50, 50));
+        for x in 0..100 {
+            for y in 0..100 {
+                if lit(&image, x, y) {
+                    let (dx, dy) = ((x - 50) as f32 / 30.0, (y - 50) as f32 / 10.0);
+                    assert!((dx.hypot(dy) - 1.0).abs() < 0.1, "({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn filled_ellipse_is_solid() {
+        let mut image = Image::new(100, 100);
+        image.draw_ellipse(50, 50, 30, 10, white(), true);
+        assert!(lit(&image, 50, 50));
+        assert!(lit(&image, 75, 52));
+        assert!(!lit(&image, 75, 58));
+        // about pi * 30 * 10 pixels
+        let area = lit_count(&image) as f32;
+        assert!((area / (f32::consts::PI * 300.0) - 1.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn flat_ellipse_is_a_line() {
+        let mut image = Image::new(100, 100);
+        image.draw_ellipse(50, 50, 20, 0, white(), false);
+        assert_eq!(lit_count(&image), 41);
+        assert!(lit(&image, 30, 50) && lit(&image, 70, 50));
+    }
+
+    #[test]
+    fn thick_ellipse_outline_is_as_wide_as_the_line() {
+        let overlay = Overlay {
+            shape: Shape::Ellipse {
+                cx: 50.0,
+                cy: 50.0,
+                rx: 30.0,
+                ry: 20.0,
+            },
+            fill: None,
+            color: white(),
+        };
+        let mut thin = Image::new(100, 100);
+        thin.draw_overlay(&overlay);
+        let mut thick = Image::new(100, 100);
+        thick.line_width = 5.0;
+        thick.draw_overlay(&overlay);
+        assert!(!lit(&thin, 82, 50) && lit(&thick, 82, 50));
+        assert!(!lit(&thin, 78, 50) && lit(&thick, 78, 50));
+        assert!(!lit(&thick, 50, 50));
+    }
+}
@@ -0,0 +1,351 @@
+use crate::gmath::{cross_product, dot_product, normalize, subtract};
+use crate::mesh::Mesh;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// How much more an edge on the border of a mesh counts than the faces, so borders keep their shape
+const BORDER_WEIGHT: f64 = 100.0;
+
+/// The sum of the squared distances to a set of planes, stored as the 10 different values of the
+/// symmetric 4x4 matrix q where the error of point p is [p 1] q [p 1]^T:
+/// (aa, ab, ac, ad, bb, bc, bd, cc, cd, dd)
+type Quadric = [f64; 10];
+
+/// The quadric for the plane with the given normal through point, counted weight times
+fn plane_quadric(normal: &[f32; 3], point: &[f32; 3], weight: f64) -> Quadric {
+    let (a, b, c) = (normal[0] as f64, normal[1] as f64, normal[2] as f64);
+    let d = -(a * point[0] as f64 + b * point[1] as f64 + c * point[2] as f64);
+    [
+        a * a,
+        a * b,
+        a * c,
+        a * d,
+        b * b,
+        b * c,
+        b * d,
+        c * c,
+        c * d,
+        d * d,
+    ]
+    .map(|q| q * weight)
+}
+
+fn add_quadric(q: &mut Quadric, other: &Quadric) {
+    for (value, add) in q.iter_mut().zip(other) {
+        *value += add;
+    }
+}
+
+/// The sum of the squared distances from p to the planes in q
+fn quadric_error(q: &Quadric, p: &[f64; 3]) -> f64 {
+    let [x, y, z] = *p;
+    q[0] * x * x
+        + 2.0 * q[1] * x * y
+        + 2.0 * q[2] * x * z
+        + 2.0 * q[3] * x
+        + q[4] * y * y
+        + 2.0 * q[5] * y * z
+        + 2.0 * q[6] * y
+        + q[7] * z * z
+        + 2.0 * q[8] * z
+        + q[9]
+}
+
+/// The point with the smallest error for q, or None if there isn't just one
+/// (like when all the planes are parallel)
+fn quadric_minimum(q: &Quadric) -> Option<[f64; 3]> {
+    // solve the 3x3 system for where the error stops changing, with cramer's rule
+    let m = [[q[0], q[1], q[2]], [q[1], q[4], q[5]], [q[2], q[5], q[7]]];
+    let rhs = [-q[3], -q[6], -q[8]];
+    let det = |m: &[[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(&m);
+    if d.abs() < 1e-10 {
+        return None;
+    }
+    let mut result = [0.0; 3];
+    for (axis, value) in result.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][axis] = rhs[row];
+        }
+        *value = det(&replaced) / d;
+    }
+    Some(result)
+}
+
+/// Joining point v into point u, moving u to position
+struct Collapse {
+    cost: f64,
+    u: usize,
+    v: usize,
+    /// the versions of u and v when this was worked out, so it can be thrown out if either has changed
+    versions: (u32, u32),
+    position: [f32; 3],
+}
+
+impl Collapse {
+    fn new(
+        u: usize,
+        v: usize,
+        quadrics: &[Quadric],
+        positions: &[[f32; 3]],
+        versions: &[u32],
+    ) -> Collapse {
+        let mut q = quadrics[u];
+        add_quadric(&mut q, &quadrics[v]);
+        let (a, b) = (positions[u], positions[v]);
+        let to_f64 = |p: [f32; 3]| [p[0] as f64, p[1] as f64, p[2] as f64];
+        // use the best point if there is one, otherwise the best of the ends and the middle
+        let choices = match quadric_minimum(&q) {
+            Some(best) => vec![best],
+            None => vec![
+                to_f64(a),
+                to_f64(b),
+                to_f64([
+                    (a[0] + b[0]) / 2.0,
+                    (a[1] + b[1]) / 2.0,
+                    (a[2] + b[2]) / 2.0,
+                ]),
+            ],
+        };
+        let (cost, best) = choices
+            .iter()
+            .map(|p| (quadric_error(&q, p), p))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .unwrap();
+        Collapse {
+            cost: cost.max(0.0),
+            u,
+            v,
+            versions: (versions[u], versions[v]),
+            position: [best[0] as f32, best[1] as f32, best[2] as f32],
+        }
+    }
+}
+
+// the heap gives back the biggest first, so the cheapest collapse has to count as the biggest
+impl Ord for Collapse {
+    fn cmp(&self, other: &Collapse) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Collapse) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Collapse) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+/// The points that share a triangle in around with me
+fn neighbors(faces: &[[usize; 3]], around: &[usize], me: usize) -> Vec<usize> {
+    let mut points: Vec<usize> = around
+        .iter()
+        .flat_map(|&f| faces[f])
+        .filter(|&p| p != me)
+        .collect();
+    points.sort_unstable();
+    points.dedup();
+    points
+}
+
+impl Mesh {
+    /// Removes triangles by joining the ends of edges together, cheapest first, until there are
+    /// no more than target triangles left or the next join would move the surface more than
+    /// max_error away from where it started.
+    ///
+    /// This is Garland and Heckbert's quadric error metric: each point keeps track of the planes
+    /// of the triangles that were around it, and the cost of a join is the sum of the squared
+    /// distances from the new point to all those planes. Joins that would flip a triangle over
+    /// or pinch the mesh together are skipped.
+    pub fn simplify(&mut self, target: usize, max_error: f32) {
+        let positions = &mut self.positions;
+        let mut faces = self.indices.clone();
+        let mut alive = vec![true; faces.len()];
+        let mut vertex_faces: Vec<Vec<usize>> = vec![vec![]; positions.len()];
+        let mut quadrics: Vec<Quadric> = vec![[0.0; 10]; positions.len()];
+        let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let face_normal = |positions: &[[f32; 3]], [a, b, c]: [usize; 3]| {
+            let mut normal = cross_product(
+                &subtract(&positions[b], &positions[a]),
+                &subtract(&positions[c], &positions[a]),
+            );
+            normalize(&mut normal);
+            normal
+        };
+        for (f, &face) in faces.iter().enumerate() {
+            let plane = plane_quadric(&face_normal(positions, face), &positions[face[0]], 1.0);
+            for (k, &corner) in face.iter().enumerate() {
+                vertex_faces[corner].push(f);
+                add_quadric(&mut quadrics[corner], &plane);
+                let other = face[(k + 1) % 3];
+                edge_faces
+                    .entry((corner.min(other), corner.max(other)))
+                    .or_default()
+                    .push(f);
+            }
+        }
+        // a wall standing up along each border edge keeps the border from moving
+        for (&(a, b), around) in &edge_faces {
+            if around.len() == 1 {
+                let mut wall = cross_product(
+                    &subtract(&positions[b], &positions[a]),
+                    &face_normal(positions, faces[around[0]]),
+                );
+                normalize(&mut wall);
+                let plane = plane_quadric(&wall, &positions[a], BORDER_WEIGHT);
+                add_quadric(&mut quadrics[a], &plane);
+                add_quadric(&mut quadrics[b], &plane);
+            }
+        }
+
+        let mut versions = vec![0; positions.len()];
+        let mut heap: BinaryHeap<Collapse> = edge_faces
+            .keys()
+            .map(|&(u, v)| Collapse::new(u, v, &quadrics, positions, &versions))
+            .collect();
+        let max_cost = (max_error as f64) * (max_error as f64);
+        let mut count = faces.len();
+        while count > target {
+            let collapse = match heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (u, v) = (collapse.u, collapse.v);
+            if collapse.versions != (versions[u], versions[v]) {
+                continue;
+            }
+            if collapse.cost > max_cost {
+                break;
+            }
+
+            let around_u: Vec<usize> = vertex_faces[u]
+                .iter()
+                .copied()
+                .filter(|&f| alive[f])
+                .collect();
+            let around_v: Vec<usize> = vertex_faces[v]
+                .iter()
+                .copied()
+                .filter(|&f| alive[f])
+                .collect();
+            // if u and v share more neighbors than the triangles between them,
+            // joining them would pinch the mesh together
+            let shared_faces = around_u.iter().filter(|f| faces[**f].contains(&v)).count();
+            let neighbors_v = neighbors(&faces, &around_v, v);
+            let shared_neighbors = neighbors(&faces, &around_u, u)
+                .iter()
+                .filter(|p| neighbors_v.contains(p))
+                .count();
+            if shared_neighbors > shared_faces {
+                continue;
+            }
+            let flips = around_u.iter().chain(&around_v).any(|&f| {
+                let face = faces[f];
+                if face.contains(&u) && face.contains(&v) {
+                    return false;
+                }
+                let mut moved = [positions[face[0]], positions[face[1]], positions[face[2]]];
+                for (k, &corner) in face.iter().enumerate() {
+                    if corner == u || corner == v {
+                        moved[k] = collapse.position;
+                    }
+                }
+                let after = cross_product(
+                    &subtract(&moved[1], &moved[0]),
+                    &subtract(&moved[2], &moved[0]),
+                );
+                dot_product(&after, &face_normal(positions, face)) <= 0.0
+            });
+            if flips {
+                continue;
+            }
+
+            positions[u] = collapse.position;
+            let q = quadrics[v];
+            add_quadric(&mut quadrics[u], &q);
+            for &f in &around_v {
+                if faces[f].contains(&u) {
+                    alive[f] = false;
+                    count -= 1;
+                } else {
+                    for corner in faces[f].iter_mut() {
+                        if *corner == v {
+                            *corner = u;
+                        }
+                    }
+                    vertex_faces[u].push(f);
+                }
+            }
+            vertex_faces[v].clear();
+            versions[u] += 1;
+            versions[v] += 1;
+            // the edges from u are the only ones with a different cost now
+            let around_u: Vec<usize> = vertex_faces[u]
+                .iter()
+                .copied()
+                .filter(|&f| alive[f])
+                .collect();
+            for w in neighbors(&faces, &around_u, u) {
+                heap.push(Collapse::new(u, w, &quadrics, positions, &versions));
+            }
+        }
+
+        self.indices = faces
+            .iter()
+            .zip(&alive)
+            .filter(|(_, &alive)| alive)
+            .map(|(&face, _)| face)
+            .collect();
+        self.remove_degenerate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrix::Matrix;
+    use crate::mesh::{Mesh, WELD_EPSILON};
+    use std::collections::HashMap;
+
+    fn mesh_of(polygons: &Matrix) -> Mesh {
+        Mesh::from_polygons(polygons, WELD_EPSILON)
+    }
+
+    #[test]
+    fn sphere_stays_closed() {
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_icosphere(0.0, 0.0, 0.0, 100.0, 3);
+        let mut mesh = mesh_of(&polygons);
+        assert_eq!(mesh.indices.len(), 1280);
+        mesh.simplify(200, f32::INFINITY);
+        assert!(mesh.indices.len() <= 200);
+        let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+        for face in &mesh.indices {
+            for k in 0..3 {
+                let (a, b) = (face[k], face[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(edges.values().all(|&count| count == 2));
+    }
+
+    #[test]
+    fn box_keeps_its_shape() {
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_box(0.0, 0.0, 0.0, 10.0, 10.0, 10.0);
+        let mut mesh = mesh_of(&polygons);
+        mesh.simplify(0, 0.01);
+        assert_eq!(mesh.indices.len(), 12);
+    }
+}
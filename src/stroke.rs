@@ -0,0 +1,270 @@
+use crate::color::Color;
+use crate::image::Image;
+use crate::shapes::FillRule;
+use std::f32;
+
+/// How far a miter join can stick out, compared to half the line width, before it gets cut off
+/// to a bevel instead. This is the same limit SVG uses
+const MITER_LIMIT: f32 = 4.0;
+
+/// How the open ends of a thick line are drawn
+#[derive(Copy, Clone)]
+pub enum Cap {
+    /// the line stops right at its end
+    Butt,
+    /// the line ends in a half circle around its end
+    Round,
+    /// the line carries on half its width past its end
+    Square,
+}
+
+/// How the corners where two thick lines meet are drawn
+#[derive(Copy, Clone)]
+pub enum Join {
+    /// the outside edges of the lines carry on until they meet in a point
+    Miter,
+    /// the corner is rounded off
+    Round,
+    /// the corner is cut off straight across
+    Bevel,
+}
+
+impl Image {
+    /// Draws lines through each of points, and back to the start if closed, using the
+    /// current line width, caps and joins.
+    ///
+    /// Lines 1 pixel wide go through draw_edge, so they are anti-aliased if that is on. Wider
+    /// lines are filled in as polygons, which never are
+    pub fn stroke_path(&mut self, points: &[[f32; 2]], closed: bool, color: Color) {
+        let count = if closed {
+            points.len()
+        } else {
+            points.len().saturating_sub(1)
+        };
+        if self.line_width <= 1.0 {
+            for i in 0..count {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                self.draw_edge(a[0], a[1], b[0], b[1], color);
+            }
+            return;
+        }
+
+        let half = self.line_width / 2.0;
+        for i in 0..count {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            if let Some(side) = offset(&a, &b, half) {
+                self.fill_polygon(
+                    &[
+                        [a[0] + side[0], a[1] + side[1]],
+                        [b[0] + side[0], b[1] + side[1]],
+                        [b[0] - side[0], b[1] - side[1]],
+                        [a[0] - side[0], a[1] - side[1]],
+                    ],
+                    FillRule::NonZero,
+                    color,
+                );
+            }
+        }
+
+        // the corners between lines, which is every point of a closed path
+        let corners = if closed {
+            0..points.len()
+        } else {
+            1..points.len().saturating_sub(1)
+        };
+        for i in corners {
+            let before = points[(i + points.len() - 1) % points.len()];
+            let (at, after) = (points[i], points[(i + 1) % points.len()]);
+            self.draw_join(&before, &at, &after, color);
+        }
+        if !closed && !points.is_empty() {
+            let (first, last) = (points[0], points[points.len() - 1]);
+            // find the direction each end points in, skipping any repeated points
+            let start_toward = points.iter().find(|p| **p != first);
+            let end_toward = points.iter().rev().find(|p| **p != last);
+            self.draw_cap(&first, start_toward, color);
+            self.draw_cap(&last, end_toward, color);
+        }
+    }
+
+    /// Draws each line from segments, joining up the ones where each starts where the last one
+    /// ended into paths, so they get joins instead of caps between them
+    pub fn stroke_segments(&mut self, segments: &[([f32; 2], [f32; 2])], color: Color) {
+        let mut path: Vec<[f32; 2]> = vec![];
+        for (start, end) in segments {
+            if path.last() != Some(start) {
+                self.stroke_open_or_closed(&path, color);
+                path = vec![*start];
+            }
+            path.push(*end);
+        }
+        self.stroke_open_or_closed(&path, color);
+    }
+
+    /// Draws a path, as a closed one if it ends where it starts
+    fn stroke_open_or_closed(&mut self, path: &[[f32; 2]], color: Color) {
+        if path.len() > 2 && path.first() == path.last() {
+            self.stroke_path(&path[..path.len() - 1], true, color);
+        } else {
+            self.stroke_path(path, false, color);
+        }
+    }
+
+    /// Draws the cap at the end of a line, where toward is another point on the line
+    fn draw_cap(&mut self, end: &[f32; 2], toward: Option<&[f32; 2]>, color: Color) {
+        let half = self.line_width / 2.0;
+        match (self.cap, toward) {
+            (Cap::Butt, _) => {}
+            (Cap::Round, _) | (Cap::Square, None) => {
+                self.fill_polygon(&circle(end, half), FillRule::NonZero, color)
+            }
+            (Cap::Square, Some(toward)) => {
+                if let Some(side) = offset(toward, end, half) {
+                    // the line's direction is the side turned a quarter turn clockwise
+                    let ahead = [side[1], -side[0]];
+                    self.fill_polygon(
+                        &[
+                            [end[0] + side[0], end[1] + side[1]],
+                            [end[0] + side[0] + ahead[0], end[1] + side[1] + ahead[1]],
+                            [end[0] - side[0] + ahead[0], end[1] - side[1] + ahead[1]],
+                            [end[0] - side[0], end[1] - side[1]],
+                        ],
+                        FillRule::NonZero,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Fills in the gap on the outside of the corner at at, between the lines from before and to after
+    fn draw_join(&mut self, before: &[f32; 2], at: &[f32; 2], after: &[f32; 2], color: Color) {
+        let half = self.line_width / 2.0;
+        if let Join::Round = self.join {
+            self.fill_polygon(&circle(at, half), FillRule::NonZero, color);
+            return;
+        }
+        let (first, second) = match (offset(before, at, half), offset(at, after, half)) {
+            (Some(first), Some(second)) => (first, second),
+            _ => return,
+        };
+        // turning left leaves the gap on the right, and turning right leaves it on the left
+        let turn =
+            (at[0] - before[0]) * (after[1] - at[1]) - (at[1] - before[1]) * (after[0] - at[0]);
+        let sign = if turn > 0.0 { -1.0 } else { 1.0 };
+        let (first, second) = (
+            [first[0] * sign, first[1] * sign],
+            [second[0] * sign, second[1] * sign],
+        );
+        let outer_first = [at[0] + first[0], at[1] + first[1]];
+        let outer_second = [at[0] + second[0], at[1] + second[1]];
+        let mut corner = vec![*at, outer_first];
+        if let Join::Miter = self.join {
+            // the tip is along the middle of the two sides, 1 / cos(half the angle between them) away
+            let middle = [first[0] + second[0], first[1] + second[1]];
+            let length = (middle[0] * middle[0] + middle[1] * middle[1]).sqrt();
+            if length > 0.0 {
+                let cos = (middle[0] * first[0] + middle[1] * first[1]) / (length * half);
+                if cos > 0.0 && 1.0 / cos <= MITER_LIMIT {
+                    let reach = half / cos / length;
+                    corner.push([at[0] + middle[0] * reach, at[1] + middle[1] * reach]);
+                }
+            }
+        }
+        corner.push(outer_second);
+        self.fill_polygon(&corner, FillRule::NonZero, color);
+    }
+}
+
+/// The sideways offset half long from the line going from a to b, pointing to its left,
+/// or None if a and b are the same point
+fn offset(a: &[f32; 2], b: &[f32; 2], half: f32) -> Option<[f32; 2]> {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return None;
+    }
+    Some([-dy / length * half, dx / length * half])
+}
+
+/// The corners of a polygon close enough to a circle of radius r around center
+fn circle(center: &[f32; 2], r: f32) -> Vec<[f32; 2]> {
+    let pieces = ((r * 2.0).ceil() as usize).max(8);
+    (0..pieces)
+        .map(|i| {
+            let theta = 2.0 * f32::consts::PI * i as f32 / pieces as f32;
+            [center[0] + r * theta.cos(), center[1] + r * theta.sin()]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(points: &[[f32; 2]], cap: Cap, join: Join) -> Image {
+        let mut image = Image::new(120, 120);
+        image.line_width = 10.0;
+        image.cap = cap;
+        image.join = join;
+        image.stroke_path(points, false, Color::new_color(255, 255, 255));
+        image
+    }
+
+    fn lit(image: &Image, x: usize, y: usize) -> bool {
+        image.screen[image.height - 1 - y][x].r != 0
+    }
+
+    fn lit_pixels(image: &Image) -> Vec<(usize, usize)> {
+        (0..image.width)
+            .flat_map(|x| (0..image.height).map(move |y| (x, y)))
+            .filter(|&(x, y)| lit(image, x, y))
+            .collect()
+    }
+
+    const LINE: [[f32; 2]; 2] = [[20.0, 50.0], [80.0, 50.0]];
+    const CORNER: [[f32; 2]; 3] = [[20.0, 20.0], [60.0, 20.0], [60.0, 60.0]];
+
+    #[test]
+    fn caps() {
+        let butt = stroke(&LINE, Cap::Butt, Join::Miter);
+        let round = stroke(&LINE, Cap::Round, Join::Miter);
+        let square = stroke(&LINE, Cap::Square, Join::Miter);
+        for image in [&butt, &round, &square] {
+            assert!(lit(image, 50, 53) && !lit(image, 50, 57));
+        }
+        // past the end of the line
+        assert!(!lit(&butt, 17, 50) && lit(&round, 17, 50) && lit(&square, 17, 50));
+        // out by the corners of the square
+        assert!(!lit(&round, 16, 54) && lit(&square, 16, 54));
+        assert!(!lit(&round, 84, 46) && lit(&square, 84, 46));
+    }
+
+    #[test]
+    fn joins() {
+        let miter = stroke(&CORNER, Cap::Butt, Join::Miter);
+        let round = stroke(&CORNER, Cap::Butt, Join::Round);
+        let bevel = stroke(&CORNER, Cap::Butt, Join::Bevel);
+        // the outside corner, right by the point of the miter
+        assert!(lit(&miter, 64, 16) && !lit(&round, 64, 16) && !lit(&bevel, 64, 16));
+        assert!(lit(&round, 63, 17) && !lit(&bevel, 63, 17));
+        for image in [&miter, &round, &bevel] {
+            assert!(lit(image, 62, 18));
+            // the inside of the corner is left alone
+            assert!(!lit(image, 50, 30));
+        }
+    }
+
+    #[test]
+    fn sharp_miter_falls_back_to_a_bevel() {
+        let sharp = [[10.0, 50.0], [90.0, 50.0], [10.0, 60.0]];
+        let miter = stroke(&sharp, Cap::Butt, Join::Miter);
+        let bevel = stroke(&sharp, Cap::Butt, Join::Bevel);
+        assert_eq!(lit_pixels(&miter), lit_pixels(&bevel));
+        assert!((96..120).all(|x| !lit(&miter, x, 54)));
+        // a right angle is well within the limit
+        let miter = stroke(&CORNER, Cap::Butt, Join::Miter);
+        let bevel = stroke(&CORNER, Cap::Butt, Join::Bevel);
+        assert_ne!(lit_pixels(&miter), lit_pixels(&bevel));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::matrix::Matrix;
+use crate::mesh::{Mesh, WELD_EPSILON};
+use std::collections::HashMap;
+
+impl Matrix {
+    /// subdivide()
+    /// Inputs:   struct matrix * polygons
+    /// Returns:
+    ///
+    /// replaces every triangle in the polygon matrix with 4 smaller ones using Loop subdivision,
+    /// which moves the points so the shape gets smoother each time it is done.
+    /// Triangles are joined up where their corners are within WELD_EPSILON of each other,
+    /// and edges that only have a triangle on one side are kept as creases.
+    pub fn subdivide(&mut self) {
+        let mesh = Mesh::from_polygons(self, WELD_EPSILON);
+        let (positions, triangles) = (mesh.positions, mesh.indices);
+
+        // the corners across from each edge, in the triangles on either side of it
+        let mut opposite: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for &[a, b, c] in &triangles {
+            for (p, q, r) in [(a, b, c), (b, c, a), (c, a, b)] {
+                opposite.entry((p.min(q), p.max(q))).or_default().push(r);
+            }
+        }
+
+        // new points in the middle of each edge
+        let mut edge_points: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut new_positions = positions.clone();
+        for (&(p, q), across) in &opposite {
+            let (a, b) = (positions[p], positions[q]);
+            let point = if across.len() == 2 {
+                let (c, d) = (positions[across[0]], positions[across[1]]);
+                [
+                    0.375 * (a[0] + b[0]) + 0.125 * (c[0] + d[0]),
+                    0.375 * (a[1] + b[1]) + 0.125 * (c[1] + d[1]),
+                    0.375 * (a[2] + b[2]) + 0.125 * (c[2] + d[2]),
+                ]
+            } else {
+                [
+                    0.5 * (a[0] + b[0]),
+                    0.5 * (a[1] + b[1]),
+                    0.5 * (a[2] + b[2]),
+                ]
+            };
+            edge_points.insert((p, q), new_positions.len());
+            new_positions.push(point);
+        }
+
+        // move the old points towards their neighbors
+        let mut neighbors: Vec<Vec<usize>> = vec![vec![]; positions.len()];
+        let mut crease_neighbors: Vec<Vec<usize>> = vec![vec![]; positions.len()];
+        for (&(p, q), across) in &opposite {
+            neighbors[p].push(q);
+            neighbors[q].push(p);
+            if across.len() != 2 {
+                crease_neighbors[p].push(q);
+                crease_neighbors[q].push(p);
+            }
+        }
+        for (v, point) in positions.iter().enumerate() {
+            let (around, weight) = if crease_neighbors[v].is_empty() {
+                let n = neighbors[v].len() as f32;
+                let beta = if neighbors[v].len() == 3 {
+                    3.0 / 16.0
+                } else {
+                    3.0 / (8.0 * n)
+                };
+                (&neighbors[v], beta)
+            } else if crease_neighbors[v].len() == 2 {
+                (&crease_neighbors[v], 0.125)
+            } else {
+                // a corner where creases meet stays put
+                continue;
+            };
+            let mut moved = [0.0; 3];
+            for axis in 0..3 {
+                let total: f32 = around.iter().map(|&u| positions[u][axis]).sum();
+                moved[axis] = (1.0 - weight * around.len() as f32) * point[axis] + weight * total;
+            }
+            new_positions[v] = moved;
+        }
+
+        let edge = |p: usize, q: usize| edge_points[&(p.min(q), p.max(q))];
+        let mut smoothed = Mesh {
+            positions: new_positions,
+            ..Mesh::default()
+        };
+        for &[a, b, c] in &triangles {
+            let (ab, bc, ca) = (edge(a, b), edge(b, c), edge(c, a));
+            smoothed.indices.push([a, ab, ca]);
+            smoothed.indices.push([b, bc, ab]);
+            smoothed.indices.push([c, ca, bc]);
+            smoothed.indices.push([ab, bc, ca]);
+        }
+        *self = smoothed.to_polygons();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csg::tests::{assert_closed, volume};
+    use crate::matrix::Matrix;
+
+    /// Every point of polygons as (x, y, z)
+    fn points_of(polygons: &Matrix) -> Vec<[f32; 3]> {
+        let m = &polygons.matrix_array;
+        (0..m[0].len())
+            .map(|i| [m[0][i], m[1][i], m[2][i]])
+            .collect()
+    }
+
+    #[test]
+    fn box_stays_closed_and_shrinks() {
+        let mut polygons = Matrix::new(0, 0);
+        polygons.add_box(0.0, 0.0, 0.0, 10.0, 10.0, 10.0);
+        let mut last = volume(&polygons);
+        for n in 1..4 {
+            polygons.subdivide();
+            assert_eq!(polygons.matrix_array[0].len(), 3 * 12 * 4_usize.pow(n));
+            assert_closed(&polygons);
+            let now = volume(&polygons);
+            assert!(now > 0.0 && now < last);
+            last = now;
+        }
+    }
+
+    #[test]
+    fn open_edges_are_kept_as_creases() {
+        // a flat strip of 2 squares, with every edge on the outside open
+        let mut polygons = Matrix::new(0, 0);
+        for x in [0.0, 10.0] {
+            polygons.add_polygon(x, 0.0, 0.0, x + 10.0, 0.0, 0.0, x + 10.0, 10.0, 0.0);
+            polygons.add_polygon(x, 0.0, 0.0, x + 10.0, 10.0, 0.0, x, 10.0, 0.0);
+        }
+        polygons.subdivide();
+        let points = points_of(&polygons);
+        assert_eq!(points.len(), 3 * 16);
+        assert!(points.iter().all(|p| p[2] == 0.0));
+        // the bottom edge is straight, so it only gets new points along it
+        for x in [5.0, 10.0, 15.0] {
+            assert!(points.contains(&[x, 0.0, 0.0]));
+        }
+        // corners where 2 open edges meet at an angle get pulled in along them
+        assert!(points.contains(&[1.25, 1.25, 0.0]));
+        assert!(points.iter().all(|p| p[1] >= 0.0 && p[1] <= 10.0));
+    }
+}
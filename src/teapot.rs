@@ -0,0 +1,220 @@
+use crate::matrix::CurveType;
+use crate::matrix::Matrix;
+
+/// Which of TEAPOT_POINTS make up each of the Utah teapot's bezier patches.
+///
+/// Only one side is listed, the rest are made by mirroring: the first 6 patches
+/// (rim, body, lid and bottom) get mirrored 4 ways and the handle and spout 2 ways.
+#[rustfmt::skip]
+const TEAPOT_PATCHES: [[usize; 16]; 10] = [
+    // rim
+    [102, 103, 104, 105, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    // body
+    [12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27],
+    [24, 25, 26, 27, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40],
+    // lid
+    [96, 96, 96, 96, 97, 98, 99, 100, 101, 101, 101, 101, 0, 1, 2, 3],
+    [0, 1, 2, 3, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117],
+    // bottom
+    [118, 118, 118, 118, 124, 122, 119, 121, 123, 126, 125, 120, 40, 39, 38, 37],
+    // handle
+    [41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56],
+    [53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 28, 65, 66, 67],
+    // spout
+    [68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83],
+    [80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95],
+];
+
+/// The control points of the Utah teapot, with z going up from the bottom at 0
+/// to the top of the lid at 3.15
+const TEAPOT_POINTS: [[f32; 3]; 127] = [
+    [0.2, 0.0, 2.7],
+    [0.2, -0.112, 2.7],
+    [0.112, -0.2, 2.7],
+    [0.0, -0.2, 2.7],
+    [1.3375, 0.0, 2.53125],
+    [1.3375, -0.749, 2.53125],
+    [0.749, -1.3375, 2.53125],
+    [0.0, -1.3375, 2.53125],
+    [1.4375, 0.0, 2.53125],
+    [1.4375, -0.805, 2.53125],
+    [0.805, -1.4375, 2.53125],
+    [0.0, -1.4375, 2.53125],
+    [1.5, 0.0, 2.4],
+    [1.5, -0.84, 2.4],
+    [0.84, -1.5, 2.4],
+    [0.0, -1.5, 2.4],
+    [1.75, 0.0, 1.875],
+    [1.75, -0.98, 1.875],
+    [0.98, -1.75, 1.875],
+    [0.0, -1.75, 1.875],
+    [2.0, 0.0, 1.35],
+    [2.0, -1.12, 1.35],
+    [1.12, -2.0, 1.35],
+    [0.0, -2.0, 1.35],
+    [2.0, 0.0, 0.9],
+    [2.0, -1.12, 0.9],
+    [1.12, -2.0, 0.9],
+    [0.0, -2.0, 0.9],
+    [-2.0, 0.0, 0.9],
+    [2.0, 0.0, 0.45],
+    [2.0, -1.12, 0.45],
+    [1.12, -2.0, 0.45],
+    [0.0, -2.0, 0.45],
+    [1.5, 0.0, 0.225],
+    [1.5, -0.84, 0.225],
+    [0.84, -1.5, 0.225],
+    [0.0, -1.5, 0.225],
+    [1.5, 0.0, 0.15],
+    [1.5, -0.84, 0.15],
+    [0.84, -1.5, 0.15],
+    [0.0, -1.5, 0.15],
+    [-1.6, 0.0, 2.025],
+    [-1.6, -0.3, 2.025],
+    [-1.5, -0.3, 2.25],
+    [-1.5, 0.0, 2.25],
+    [-2.3, 0.0, 2.025],
+    [-2.3, -0.3, 2.025],
+    [-2.5, -0.3, 2.25],
+    [-2.5, 0.0, 2.25],
+    [-2.7, 0.0, 2.025],
+    [-2.7, -0.3, 2.025],
+    [-3.0, -0.3, 2.25],
+    [-3.0, 0.0, 2.25],
+    [-2.7, 0.0, 1.8],
+    [-2.7, -0.3, 1.8],
+    [-3.0, -0.3, 1.8],
+    [-3.0, 0.0, 1.8],
+    [-2.7, 0.0, 1.575],
+    [-2.7, -0.3, 1.575],
+    [-3.0, -0.3, 1.35],
+    [-3.0, 0.0, 1.35],
+    [-2.5, 0.0, 1.125],
+    [-2.5, -0.3, 1.125],
+    [-2.65, -0.3, 0.9375],
+    [-2.65, 0.0, 0.9375],
+    [-2.0, -0.3, 0.9],
+    [-1.9, -0.3, 0.6],
+    [-1.9, 0.0, 0.6],
+    [1.7, 0.0, 1.425],
+    [1.7, -0.66, 1.425],
+    [1.7, -0.66, 0.6],
+    [1.7, 0.0, 0.6],
+    [2.6, 0.0, 1.425],
+    [2.6, -0.66, 1.425],
+    [3.1, -0.66, 0.825],
+    [3.1, 0.0, 0.825],
+    [2.3, 0.0, 2.1],
+    [2.3, -0.25, 2.1],
+    [2.4, -0.25, 2.025],
+    [2.4, 0.0, 2.025],
+    [2.7, 0.0, 2.4],
+    [2.7, -0.25, 2.4],
+    [3.3, -0.25, 2.4],
+    [3.3, 0.0, 2.4],
+    [2.8, 0.0, 2.475],
+    [2.8, -0.25, 2.475],
+    [3.525, -0.25, 2.49375],
+    [3.525, 0.0, 2.49375],
+    [2.9, 0.0, 2.475],
+    [2.9, -0.15, 2.475],
+    [3.45, -0.15, 2.5125],
+    [3.45, 0.0, 2.5125],
+    [2.8, 0.0, 2.4],
+    [2.8, -0.15, 2.4],
+    [3.2, -0.15, 2.4],
+    [3.2, 0.0, 2.4],
+    [0.0, 0.0, 3.15],
+    [0.8, 0.0, 3.15],
+    [0.8, -0.45, 3.15],
+    [0.45, -0.8, 3.15],
+    [0.0, -0.8, 3.15],
+    [0.0, 0.0, 2.85],
+    [1.4, 0.0, 2.4],
+    [1.4, -0.784, 2.4],
+    [0.784, -1.4, 2.4],
+    [0.0, -1.4, 2.4],
+    [0.4, 0.0, 2.55],
+    [0.4, -0.224, 2.55],
+    [0.224, -0.4, 2.55],
+    [0.0, -0.4, 2.55],
+    [1.3, 0.0, 2.55],
+    [1.3, -0.728, 2.55],
+    [0.728, -1.3, 2.55],
+    [0.0, -1.3, 2.55],
+    [1.3, 0.0, 2.4],
+    [1.3, -0.728, 2.4],
+    [0.728, -1.3, 2.4],
+    [0.0, -1.3, 2.4],
+    [0.0, 0.0, 0.0],
+    [1.425, -0.798, 0.0],
+    [1.5, 0.0, 0.075],
+    [1.425, 0.0, 0.0],
+    [0.798, -1.425, 0.0],
+    [0.0, -1.5, 0.075],
+    [0.0, -1.425, 0.0],
+    [1.5, -0.84, 0.075],
+    [0.84, -1.5, 0.075],
+];
+
+impl Matrix {
+    /// add_teapot()
+    /// Inputs:   struct matrix * polygons
+    ///             double cx
+    ///             double cy
+    ///             double cz
+    ///             double size
+    ///             int step
+    /// Returns:
+    ///
+    /// adds the Utah teapot centered at (cx, cy, cz), standing up along y
+    /// with its spout pointing towards +x. At size 1 it is 3.15 tall and
+    /// about 6.4 wide, so use something like 50 to fill the screen.
+    /// Each of the 32 patches is split into step by step squares.
+    pub fn add_teapot(&mut self, cx: f32, cy: f32, cz: f32, size: f32, step: i32) {
+        for (p, patch) in TEAPOT_PATCHES.iter().enumerate() {
+            // (mirror x, mirror y) for each copy of the patch
+            let mut mirrors = vec![(false, false), (false, true)];
+            if p < 6 {
+                mirrors.push((true, false));
+                mirrors.push((true, true));
+            }
+            for (mirror_x, mirror_y) in mirrors {
+                let mut controls = Matrix::new(0, 0);
+                for row in 0..4 {
+                    for col in 0..4 {
+                        // mirroring once turns the patch inside out, so run the columns backwards to fix it
+                        let col = if mirror_x != mirror_y { 3 - col } else { col };
+                        let [mut x, mut y, z] = TEAPOT_POINTS[patch[row * 4 + col]];
+                        if mirror_x {
+                            x = -x;
+                        }
+                        if mirror_y {
+                            y = -y;
+                        }
+                        // the teapot data has z going up, so turn it so y goes up instead
+                        controls.add_point(cx + x * size, cy + (z - 1.575) * size, cz - y * size);
+                    }
+                }
+                self.add_patch(&controls, step, &CurveType::Bezier);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csg::tests::volume;
+    use crate::matrix::Matrix;
+
+    #[test]
+    fn teapot_faces_out() {
+        // the teapot is not closed, as the lid sits on the rim and the handle and spout
+        // poke into the body, but it is close enough for its volume to show which way it faces
+        let mut teapot = Matrix::new(0, 0);
+        teapot.add_teapot(0.0, 0.0, 0.0, 1.0, 8);
+        let v = volume(&teapot);
+        // the body is at most 2 across and 3.15 tall
+        assert!(v > 20.0 && v < std::f32::consts::PI * 4.0 * 3.15, "{}", v);
+    }
+}